@@ -576,7 +576,10 @@ fn evaluate_input(
     let input_id = graph[node_id].get_input(param_name)?;
 
     // The output of another node is connected.
-    if let Some(other_output_id) = graph.connection(input_id) {
+    let connected_output = graph
+        .iter_connections()
+        .find_map(|(input, output)| (input == input_id).then_some(output));
+    if let Some(other_output_id) = connected_output {
         // The value was already computed due to the evaluation of some other
         // node. We simply return value from the cache.
         if let Some(other_value) = outputs_cache.get(&other_output_id) {
@@ -215,10 +215,7 @@ where
                 node_rects: &mut node_rects,
                 node_id,
                 ongoing_drag: self.connection_in_progress,
-                selected: self
-                    .selected_nodes
-                    .iter()
-                    .any(|selected| *selected == node_id),
+                selected: self.selected_nodes.contains(&node_id),
                 pan: self.pan_zoom.pan + editor_rect.min.to_vec2(),
             }
             .show(ui, user_state);
@@ -291,7 +288,7 @@ where
                     start_pos,
                 ),
             };
-            draw_connection(ui.painter(), src_pos, dst_pos, connection_color);
+            draw_connection(ui.painter(), src_pos, dst_pos, connection_color, 5.0);
         }
 
         for (input, output) in self.graph.iter_connections() {
@@ -302,7 +299,11 @@ where
             let connection_color = port_type.data_type_color(user_state);
             let src_pos = port_locations[&AnyParameterId::Output(output)];
             let dst_pos = port_locations[&AnyParameterId::Input(input)];
-            draw_connection(ui.painter(), src_pos, dst_pos, connection_color);
+            if self.highlighted_connections.contains(&output) {
+                let highlight_color = ui.visuals().selection.bg_fill;
+                draw_connection(ui.painter(), src_pos, dst_pos, highlight_color, 11.0);
+            }
+            draw_connection(ui.painter(), src_pos, dst_pos, connection_color, 5.0);
         }
 
         /* Handle responses from drawing nodes */
@@ -447,8 +448,8 @@ where
     }
 }
 
-fn draw_connection(painter: &Painter, src_pos: Pos2, dst_pos: Pos2, color: Color32) {
-    let connection_stroke = egui::Stroke { width: 5.0, color };
+fn draw_connection(painter: &Painter, src_pos: Pos2, dst_pos: Pos2, color: Color32, width: f32) {
+    let connection_stroke = egui::Stroke { width, color };
 
     let control_scale = ((dst_pos.x - src_pos.x) / 2.0).max(30.0);
     let src_control = src_pos + Vec2::X * control_scale;
@@ -536,7 +537,7 @@ where
 
                 responses.push(NodeResponse::DisconnectEvent {
                     input: corresp_input,
-                    output: output,
+                    output,
                 });
             } else {
                 responses.push(NodeResponse::ConnectEventStarted(node_id, param_id));
@@ -711,8 +712,7 @@ where
                 responses.extend(
                     self.graph[self.node_id]
                         .user_data
-                        .output_ui(ui, self.node_id, self.graph, user_state, &param_name)
-                        .into_iter(),
+                        .output_ui(ui, self.node_id, self.graph, user_state, &param_name),
                 );
 
                 self.graph[self.node_id].user_data.separator(
@@ -752,7 +752,7 @@ where
         for ((_, param), port_height) in self.graph[self.node_id]
             .inputs
             .iter()
-            .zip(input_port_heights.into_iter())
+            .zip(input_port_heights)
         {
             let should_draw = match self.graph[*param].kind() {
                 InputParamKind::ConstantOnly => false,
@@ -780,7 +780,7 @@ where
         for ((_, param), port_height) in self.graph[self.node_id]
             .outputs
             .iter()
-            .zip(output_port_heights.into_iter())
+            .zip(output_port_heights)
         {
             let pos_right = pos2(port_right, port_height);
             Self::draw_port(
@@ -1,4 +1,4 @@
-use super::{AnyParameterId, Graph, NodeFinder, NodeId, SecondaryMap};
+use super::{AnyParameterId, Graph, NodeFinder, NodeId, OutputId, SecondaryMap};
 use std::marker::PhantomData;
 
 use eframe::egui;
@@ -33,6 +33,9 @@ pub struct GraphEditorState<NodeData, DataType, ValueType, NodeTemplate, UserSta
     pub node_finder: Option<NodeFinder<NodeTemplate>>,
     /// The panning of the graph viewport.
     pub pan_zoom: PanZoom,
+    /// Connections drawn with a highlight, identified by their output.
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    pub highlighted_connections: Vec<OutputId>,
     pub _user_state: PhantomData<fn() -> UserState>,
 }
 
@@ -63,6 +66,7 @@ impl<NodeData, DataType, ValueType, NodeKind, UserState> Default
             node_positions: SecondaryMap::default(),
             node_finder: Option::default(),
             pan_zoom: PanZoom::default(),
+            highlighted_connections: Vec::default(),
             _user_state: PhantomData,
         }
     }
@@ -1,57 +1,53 @@
-use std::{collections::HashMap, path::PathBuf};
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::path::PathBuf;
 
 use eframe::egui;
 use eframe::egui::{Pos2, Widget};
 use egui_notify::Anchor;
 use map_macro::hash_map;
-use nftables::expr::Expression;
-use nftables::schema::{NfCmd, NfListObject, NfObject, Nftables};
-use nftables::stmt::NATFamily;
-use serde::{Deserialize, Serialize};
-use serde_json::Map;
 use tap::Tap;
 
-use egui_node_graph::{GraphEditorState, InputId, NodeId, NodeTemplateTrait, OutputId};
-use nf_graph::{DataType, NFGraphState, NFNodeData as NodeData, NodeTemplateIter, ValueType};
+use egui_node_graph::{NodeId, NodeTemplateTrait};
+use netgraph_core::compile::BaseChain;
+use netgraph_core::nf_graph::simulate::{self, PacketFields, Trace};
+use netgraph_core::nf_graph::{
+    params, Backend, LoadBalanceTarget, NFGraphState, NFNodeData as NodeData, NFNodeData,
+    NatTarget, NodeTemplateIter, TcpFlagsOptions, TimeOptions,
+};
+use netgraph_core::plugin::Plugin;
+use netgraph_core::{bundle, import, storage, topology};
+use netgraph_core::{Diagnostic, Evaluator, NFEditorState, SavedGraph};
 
-use crate::app::nf_graph::{NFDirection, NFNodeData};
-use crate::app::plugin::Plugin;
-
-mod nf_graph;
-mod plugin;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct Predicate {
-    variant: String,
-    params: HashMap<String, String>,
-}
-type PredicateSet = Vec<Predicate>;
-type NodeOutputs = HashMap<String, Vec<PredicateSet>>;
-type NodeOutputDB = HashMap<NodeId, NodeOutputs>;
+pub mod cli;
 
 pub struct App {
-    editor_state: GraphEditorState<NodeData, DataType, ValueType, NodeData, NFGraphState>,
+    editor_state: NFEditorState,
     user_state: NFGraphState,
     source_node: NodeId,
     all_kinds: NodeTemplateIter,
     current_graph_path: Option<PathBuf>,
     toasts: egui_notify::Toasts,
+    diagnostics: Vec<Diagnostic>,
+    export_with_errors: bool,
+    simulator: PacketFields,
+    trace: Option<Trace>,
+    import_report: Vec<String>,
 }
 
 impl Default for App {
     fn default() -> Self {
         let mut slf = Self {
-            editor_state: GraphEditorState::default(),
-            user_state: NFGraphState {
-                active_node: None,
-                plugins: HashMap::new(),
-            },
+            editor_state: NFEditorState::default(),
+            user_state: NFGraphState::default(),
             source_node: NodeId::default(),
             all_kinds: NodeTemplateIter::new(Vec::new()),
             current_graph_path: None,
             toasts: egui_notify::Toasts::new().with_anchor(Anchor::BottomRight),
+            diagnostics: vec![],
+            export_with_errors: false,
+            simulator: PacketFields::default(),
+            trace: None,
+            import_report: vec![],
         };
         slf.new_graph();
         slf
@@ -74,7 +70,7 @@ impl eframe::App for App {
                     let plugins = &self.user_state.plugins;
                     ui.label(format!("ID: {}", node.get_id()));
                     if let NFNodeData::Custom { plugin, id, .. } = &node {
-                        ui.label(format!("{}", plugins[plugin][id].display_name));
+                        ui.label(&plugins[plugin][id].display_name);
                     } else {
                         ui.label(format!("{node}"));
                     }
@@ -123,27 +119,173 @@ impl eframe::App for App {
                             ui.label("Match protocol:");
                             egui::TextEdit::singleline(protocol).ui(ui);
                         }
+                        NFNodeData::TcpFlagsFilter(options) => tcp_flags_ui(ui, options),
+                        NFNodeData::TimeFilter(options) => time_ui(ui, options),
+                        NFNodeData::IcmpTypeFilter(types) => icmp_types_ui(ui, types),
                         NFNodeData::InterfaceFilter(ifname) => {
                             ui.label("Match interface:");
                             egui::TextEdit::singleline(ifname).ui(ui);
                         }
-                        NFNodeData::DestinationNAT(addr) => {
-                            ui.label("Direct packet to:");
-                            egui::TextEdit::singleline(addr).ui(ui);
+                        NFNodeData::SocketUserFilter(users) => {
+                            ui.label("Match socket user:");
+                            egui::TextEdit::singleline(users).ui(ui);
+                        }
+                        NFNodeData::SocketGroupFilter(groups) => {
+                            ui.label("Match socket group:");
+                            egui::TextEdit::singleline(groups).ui(ui);
+                        }
+                        NFNodeData::CgroupFilter(path) => {
+                            ui.label("Match cgroup path:");
+                            egui::TextEdit::singleline(path).ui(ui);
+                        }
+                        NFNodeData::CtStateFilter(states) => {
+                            ui.label("Match connection state:");
+                            for state in params::CT_STATES {
+                                let mut checked = states.iter().any(|s| s == state);
+                                if ui.checkbox(&mut checked, state).changed() {
+                                    // keep the states in a stable order
+                                    *states = params::CT_STATES
+                                        .iter()
+                                        .filter(|s| {
+                                            if **s == state {
+                                                checked
+                                            } else {
+                                                states.iter().any(|other| other == *s)
+                                            }
+                                        })
+                                        .map(|s| s.to_string())
+                                        .collect();
+                                }
+                            }
+                        }
+                        NFNodeData::Reject(reason) => {
+                            ui.label("Reply with:");
+                            egui::ComboBox::from_id_source("reject_reason")
+                                .selected_text(reason.as_str())
+                                .show_ui(ui, |ui| {
+                                    for option in params::REJECT_REASONS {
+                                        ui.selectable_value(reason, option.to_string(), option);
+                                    }
+                                });
+                        }
+                        NFNodeData::DestinationNAT(target) => {
+                            nat_target_ui(ui, target, Some("Direct packet to:"));
+                        }
+                        NFNodeData::SourceNAT(target) => {
+                            nat_target_ui(ui, target, Some("Send packet from:"));
+                        }
+                        NFNodeData::LoadBalanceNAT(target) => load_balance_ui(ui, target),
+                        NFNodeData::Masquerade(target) | NFNodeData::Redirect(target) => {
+                            nat_target_ui(ui, target, None);
+                        }
+                        NFNodeData::MarkFilter(options) => {
+                            ui.label("Match mark:");
+                            egui::TextEdit::singleline(&mut options.mark)
+                                .hint_text("0x1")
+                                .ui(ui);
+                            ui.checkbox(&mut options.conntrack, "Mark of the connection");
+                        }
+                        NFNodeData::SetMark(options) => {
+                            ui.label("Set mark:");
+                            egui::TextEdit::singleline(&mut options.mark)
+                                .hint_text("0x1")
+                                .ui(ui);
+                            ui.checkbox(&mut options.conntrack, "Mark of the connection");
+                        }
+                        NFNodeData::RouteVia(options) => {
+                            ui.label("Mark:");
+                            egui::TextEdit::singleline(&mut options.mark)
+                                .hint_text("0x1")
+                                .ui(ui);
+                            ui.label("Routing table:");
+                            egui::TextEdit::singleline(&mut options.table)
+                                .hint_text("100")
+                                .ui(ui);
+                            ui.label("Gateway:");
+                            egui::TextEdit::singleline(&mut options.gateway).ui(ui);
+                            ui.label("Interface:");
+                            egui::TextEdit::singleline(&mut options.device).ui(ui);
                         }
-                        NFNodeData::SourceNAT(addr) => {
-                            ui.label("Send packet from:");
-                            egui::TextEdit::singleline(addr).ui(ui);
+                        NFNodeData::RateLimit(options) => {
+                            ui.label("Rate:");
+                            egui::TextEdit::singleline(&mut options.rate)
+                                .hint_text("10/second")
+                                .ui(ui);
+                            ui.label("Burst:");
+                            egui::TextEdit::singleline(&mut options.burst).ui(ui);
+                            ui.checkbox(&mut options.per_source, "Limit every source separately");
+                        }
+                        NFNodeData::Quota(quota) => {
+                            ui.label("Quota:");
+                            egui::TextEdit::singleline(quota)
+                                .hint_text("500 mbytes")
+                                .ui(ui);
+                        }
+                        NFNodeData::Log(options) => {
+                            ui.label("Prefix:");
+                            egui::TextEdit::singleline(&mut options.prefix).ui(ui);
+                            ui.label("Level:");
+                            egui::ComboBox::from_id_source("log_level")
+                                .selected_text(options.level.as_str())
+                                .show_ui(ui, |ui| {
+                                    for level in params::LOG_LEVELS {
+                                        ui.selectable_value(
+                                            &mut options.level,
+                                            level.to_string(),
+                                            level,
+                                        );
+                                    }
+                                });
+                            ui.label("Netlink group (replaces the level):");
+                            egui::TextEdit::singleline(&mut options.group).ui(ui);
+                            ui.label("At most:");
+                            egui::TextEdit::singleline(&mut options.rate)
+                                .hint_text("10/minute")
+                                .ui(ui);
+                        }
+                        NFNodeData::Counter(name) => {
+                            ui.label("Counter name (empty to count in the rule):");
+                            egui::TextEdit::singleline(name).ui(ui);
                         }
                         NFNodeData::Localhost
+                        | NFNodeData::Router
                         | NFNodeData::Accept
                         | NFNodeData::Drop
                         | NFNodeData::Source
                         | NFNodeData::FamilySplitter => {}
                     }
+                    if let Some(err) = self.user_state.validation.error(node_id, node) {
+                        ui.colored_label(ui.visuals().error_fg_color, err);
+                    }
                     return;
                 }
 
+                let invalid_nodes = self
+                    .user_state
+                    .validation
+                    .invalid_nodes(&self.editor_state.graph);
+                if !invalid_nodes.is_empty() {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("{} node(s) have invalid parameters", invalid_nodes.len()),
+                    );
+                    for (node_id, err) in invalid_nodes {
+                        let label = self.editor_state.graph.nodes[node_id].label.clone();
+                        if ui.link(format!("{label}: {err}")).clicked() {
+                            self.user_state.active_node = Some(node_id);
+                        }
+                    }
+                    ui.separator();
+                }
+
+                egui::CollapsingHeader::new("Chains").show(ui, |ui| {
+                    self.chain_settings_ui(ui);
+                });
+
+                egui::CollapsingHeader::new("Simulator").show(ui, |ui| {
+                    self.simulator_ui(ui);
+                });
+
                 if ui.button("Import an extension").clicked() {
                     match self.import_extension() {
                         Ok(()) => self.toasts.success("Extension imported successfully"),
@@ -151,6 +293,30 @@ impl eframe::App for App {
                     };
                 }
 
+                if !self.diagnostics.is_empty() {
+                    egui::CollapsingHeader::new(format!(
+                        "Diagnostics ({})",
+                        self.diagnostics.len()
+                    ))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for diagnostic in &self.diagnostics {
+                            let text = egui::RichText::new(diagnostic.to_string())
+                                .color(ui.visuals().error_fg_color);
+                            if ui.link(text).clicked() {
+                                // the node may have been deleted since the export
+                                self.user_state.active_node = diagnostic
+                                    .node
+                                    .filter(|id| self.editor_state.graph.nodes.contains_key(*id));
+                            }
+                        }
+                        ui.checkbox(
+                            &mut self.export_with_errors,
+                            "Export anyway, leaving failed paths out",
+                        );
+                    });
+                }
+
                 if ui.button("Export configuration").clicked() {
                     match self.export_configuration() {
                         Ok(()) => self.toasts.success("Configuration exported successfully"),
@@ -158,6 +324,25 @@ impl eframe::App for App {
                     };
                 };
 
+                if ui.button("Import ruleset").clicked() {
+                    match self.import_ruleset() {
+                        Ok(()) => self.toasts.success("Ruleset imported"),
+                        Err(err) => self.toasts.error(err.to_string()),
+                    };
+                }
+
+                if !self.import_report.is_empty() {
+                    egui::CollapsingHeader::new(format!(
+                        "Not imported ({})",
+                        self.import_report.len()
+                    ))
+                    .show(ui, |ui| {
+                        for item in &self.import_report {
+                            ui.label(item);
+                        }
+                    });
+                }
+
                 if ui.button("New node graph").clicked() {
                     self.new_graph();
                 }
@@ -179,7 +364,7 @@ impl eframe::App for App {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             use egui_node_graph::NodeResponse::{ConnectEventEnded, DeleteNodeFull, User};
-            use nf_graph::NodeResponse::SelectNode;
+            use netgraph_core::nf_graph::NodeResponse::SelectNode;
 
             let responses = self.editor_state.draw_graph_editor(
                 ui,
@@ -194,15 +379,16 @@ impl eframe::App for App {
                         input: input_id,
                         output: output_id,
                     } => {
-                        if self.break_loops(output_id).is_some() {
-                            self.editor_state.graph.connections.remove(output_id);
+                        let graph = &mut self.editor_state.graph;
+                        if topology::closes_loop(graph, output_id) {
+                            graph.connections.remove(output_id);
                         }
-                        self.propagate_data_types(input_id, output_id);
+                        topology::propagate_data_types(graph, input_id, output_id);
                     }
-                    DeleteNodeFull { node_id, .. } => {
-                        if self.user_state.active_node == Some(node_id) {
-                            self.user_state.active_node = None;
-                        }
+                    DeleteNodeFull { node_id, .. }
+                        if self.user_state.active_node == Some(node_id) =>
+                    {
+                        self.user_state.active_node = None;
                     }
                     _ => {}
                 }
@@ -229,648 +415,115 @@ impl App {
         self.all_kinds = NodeTemplateIter::new(custom_kinds);
     }
 
-    fn propagate_data_types(&mut self, input_id: InputId, output_id: OutputId) -> Option<()> {
-        let input = self.editor_state.graph.inputs.get(input_id)?;
-        let output = self.editor_state.graph.outputs.get(output_id)?;
-        if output.typ.direction == NFDirection::Either {
-            return Some(());
-        }
-        if input.typ.direction != NFDirection::Either {
-            return Some(());
-        }
-        self.propagate_to_node(input_id, output.typ.direction)
+    /// Validates every node anew, unlike the side panel, as files named by
+    /// the nodes may have changed since they were edited.
+    fn invalid_nodes(&self) -> Vec<(NodeId, String)> {
+        self.evaluator().invalid_nodes()
     }
 
-    fn propagate_to_node(&mut self, input_id: InputId, direction: NFDirection) -> Option<()> {
-        self.editor_state
-            .graph
-            .inputs
-            .get_mut(input_id)?
-            .typ
-            .direction = direction;
-        let input = self.editor_state.graph.inputs.get(input_id)?;
-        let node = self.editor_state.graph.nodes.get(input.node)?;
-        for (_, output_id) in node.outputs.clone() {
-            self.editor_state
-                .graph
-                .outputs
-                .get_mut(output_id)?
-                .typ
-                .direction = direction;
-            if let Some(connected_input_id) = self.editor_state.graph.connections.get(output_id) {
-                self.propagate_to_node(*connected_input_id, direction);
+    fn simulator_ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("simulator").show(ui, |ui| {
+            let fields = &mut self.simulator;
+            for (label, field, hint) in [
+                ("Protocol", &mut fields.protocol, "tcp"),
+                ("Source", &mut fields.source, "10.0.0.5:5555"),
+                ("Destination", &mut fields.destination, "192.168.1.10:22"),
+                ("In interface", &mut fields.iifname, "eth0"),
+                ("Out interface", &mut fields.oifname, "eth1"),
+                ("Connection state", &mut fields.ct_state, "new"),
+                ("TCP flags", &mut fields.tcp_flags, "syn"),
+                ("ICMP type", &mut fields.icmp_type, "echo-request"),
+                ("Time (UTC)", &mut fields.time, "2024-12-24 18:00"),
+            ] {
+                ui.label(label);
+                egui::TextEdit::singleline(field).hint_text(hint).ui(ui);
+                ui.end_row();
             }
-        }
-        Some(())
-    }
-
-    fn get_receiving_node(&self, output_id: OutputId) -> Option<NodeId> {
-        let connected_input = self.editor_state.graph.connections.get(output_id)?;
-        let connected_input_node = self.editor_state.graph.inputs.get(*connected_input)?;
-        Some(connected_input_node.node)
-    }
-
-    fn get_sending_nodes(&self, input_id: InputId) -> Vec<NodeId> {
-        self.editor_state
-            .graph
-            .connections
-            .iter()
-            .filter(|(_, &iid)| iid == input_id)
-            .map(|(oid, _)| oid)
-            .filter_map(|oid| Some(self.editor_state.graph.outputs.get(oid)?.node))
-            .collect()
-    }
-
-    fn get_connected_receiver_nodes(&self, node_id: NodeId) -> Vec<NodeId> {
-        let Some(node) = self.editor_state.graph.nodes.get(node_id) else {
-            return vec![];
-        };
-        node.outputs
-            .iter()
-            .filter_map(|(_, output_id)| self.get_receiving_node(*output_id))
-            .collect()
-    }
-
-    fn get_connected_sender_nodes(&self, node_id: NodeId) -> Vec<NodeId> {
-        let Some(node) = self.editor_state.graph.nodes.get(node_id) else {
-            return vec![];
-        };
-        node.inputs
-            .iter()
-            .flat_map(|(_, input_id)| self.get_sending_nodes(*input_id))
-            .collect()
-    }
-
-    fn apply_custom_node(
-        &self,
-        input: &PredicateSet,
-        node_data: &NodeData,
-        output_name: &str,
-    ) -> anyhow::Result<(PredicateSet, HashMap<String, String>)> {
-        #[derive(Serialize, Deserialize)]
-        struct OutputData {
-            predicate_set: PredicateSet,
-            custom_data: HashMap<String, String>,
-        }
-        let path = self.current_graph_path.clone().unwrap();
-        let NFNodeData::Custom { plugin, id, .. } = node_data else {
-            return Err(anyhow::anyhow!("Node is not custom"));
-        };
-        let script_path = path
-            .clone()
-            .tap_mut(|s| s.as_mut_os_string().push(format!("/plugins/{plugin}/{id}")));
-        let mut child = std::process::Command::new(script_path)
-            .arg(output_name)
-            .spawn()
-            .map_err(|e| anyhow::anyhow!("Cannot execute node script: {}", e))?;
-        child
-            .stdin
-            .as_mut()
-            .unwrap()
-            .write_all(serde_json::to_string(input)?.as_bytes())?;
-        let mut output = vec![];
-        child.stdout.as_mut().unwrap().read_to_end(&mut output)?;
-        let output = String::from_utf8(output).map_err(|e| anyhow::anyhow!("{}", e))?;
-        let output_data: OutputData = serde_json::from_str(&output)
-            .map_err(|e| anyhow::anyhow!("Cannot deserialize node script output: {}", e))?;
-
-        Ok((output_data.predicate_set, output_data.custom_data))
-    }
-
-    fn apply_node(
-        &self,
-        input: &PredicateSet,
-        node_data: &NodeData,
-        output_name: &str,
-    ) -> anyhow::Result<PredicateSet> {
-        let id = node_data.get_id().clone();
-        match node_data {
-            NodeData::Custom { .. } => Ok(self.apply_custom_node(input, node_data, output_name)?.0),
-
-            NFNodeData::FileIpList(path) => {
-                let Some(path) = path else {
-                    return Err(anyhow::anyhow!("IP List file is required"));
-                };
-                let predicate = Predicate {
-                    variant: id.clone(),
-                    params: hash_map! {
-                        String::from("path") => path.to_string_lossy().to_string(),
-                        String::from("rule") => output_name.to_string(),
-                    },
-                };
-                Ok([input.clone(), vec![predicate]].concat())
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Trace").clicked() {
+                match self.simulator.packet() {
+                    Ok(packet) => {
+                        let trace = simulate::trace(
+                            &self.editor_state.graph,
+                            self.source_node,
+                            &self.user_state.chains,
+                            self.current_graph_path.as_deref(),
+                            packet,
+                        );
+                        self.editor_state.highlighted_connections = trace.connections.clone();
+                        self.trace = Some(trace);
+                    }
+                    Err(err) => {
+                        self.toasts.error(err.to_string());
+                    }
+                }
             }
-
-            NFNodeData::SourceAddressFilter(filter)
-            | NFNodeData::DestinationAddressFilter(filter)
-            | NFNodeData::SourcePortFilter(filter)
-            | NFNodeData::DestinationPortFilter(filter)
-            | NFNodeData::InterfaceFilter(filter)
-            | NFNodeData::ProtocolFilter(filter) => {
-                let predicate = Predicate {
-                    variant: id.clone(),
-                    params: hash_map! {
-                        String::from("filter") => filter.to_string(),
-                        String::from("rule") => output_name.to_string(),
-                    },
-                };
-                Ok([input.clone(), vec![predicate]].concat())
+            if ui.button("Clear").clicked() {
+                self.editor_state.highlighted_connections.clear();
+                self.trace = None;
             }
+        });
 
-            NodeData::SourceNAT(addr) | NodeData::DestinationNAT(addr) => {
-                let predicate = Predicate {
-                    variant: id.clone(),
-                    params: hash_map! {
-                        String::from("addr") => addr.clone(),
-                    },
-                };
-                Ok([input.clone(), vec![predicate]].concat())
-            }
-            NodeData::Localhost | NodeData::Accept | NodeData::Drop => {
-                let predicate = Predicate {
-                    variant: id.clone(),
-                    params: hash_map! {},
-                };
-                Ok([input.clone(), vec![predicate]].concat())
-            }
-            NFNodeData::FamilySplitter => {
-                let predicate = Predicate {
-                    variant: id.clone(),
-                    params: hash_map! {
-                        String::from("family") => output_name.to_string(),
-                    },
-                };
-                Ok([input.clone(), vec![predicate]].concat())
+        let Some(trace) = &self.trace else {
+            return;
+        };
+        for step in &trace.steps {
+            let text = if step.note.is_empty() {
+                step.label.clone()
+            } else {
+                format!("{} → {}", step.label, step.note)
+            };
+            if ui.link(text).clicked() && self.editor_state.graph.nodes.contains_key(step.node) {
+                self.user_state.active_node = Some(step.node);
             }
-            NFNodeData::Source => Ok(vec![Predicate {
-                variant: id.clone(),
-                params: hash_map! {},
-            }]),
-        }
-    }
-
-    fn node_outputs(
-        &self,
-        node_inputs: &Vec<PredicateSet>,
-        node_id: NodeId,
-    ) -> anyhow::Result<NodeOutputs, anyhow::Error> {
-        let mut output_predicates = HashMap::new();
-        let node = self
-            .editor_state
-            .graph
-            .nodes
-            .get(node_id)
-            .ok_or(anyhow::anyhow!("Node not found"))?;
-        let node_data = &node.user_data;
-        let mut outputs = node.outputs.clone();
-
-        if outputs.is_empty() {
-            outputs.push(("terminal".into(), OutputId::default()));
         }
-        for (output_name, _) in outputs {
-            let mut node_output = Vec::new();
-            for input in node_inputs {
-                node_output.push(self.apply_node(input, node_data, &output_name)?);
-            }
-            output_predicates.insert(output_name.clone(), node_output);
+        for rewrite in &trace.rewrites {
+            ui.label(rewrite);
         }
-
-        Ok(output_predicates)
-    }
-
-    fn recurse_node_outputs(
-        &self,
-        this_node_id: NodeId,
-        node_output_db: &mut NodeOutputDB,
-    ) -> anyhow::Result<()> {
-        if node_output_db.contains_key(&this_node_id) {
-            return Ok(());
+        if !trace.rewrites.is_empty() {
+            ui.label(format!("Final packet: {}", trace.packet));
         }
-        let this_node = self
-            .editor_state
-            .graph
-            .nodes
-            .get(this_node_id)
-            .ok_or(anyhow::Error::msg("Node not found"))?;
-        let Some((_, this_node_input_id)) = this_node.inputs.first() else {
-            node_output_db.insert(
-                this_node_id,
-                self.node_outputs(&vec![PredicateSet::new()], this_node_id)?,
-            );
-            return Ok(());
-        };
-        let this_node_inputs: Vec<PredicateSet> = self
-            .get_connected_sender_nodes(this_node_id)
-            .iter()
-            .filter_map(|&dep_node_id| {
-                self.recurse_node_outputs(dep_node_id, node_output_db)
-                    .ok()?;
-                let dep_node = &self.editor_state.graph.nodes.get(dep_node_id)?;
-
-                let dep_node_outputs: Vec<_> = dep_node
-                    .outputs
-                    .iter()
-                    .filter_map(|(output_name, output_id)| {
-                        if *this_node_input_id
-                            == *self.editor_state.graph.connections.get(*output_id)?
-                        {
-                            Some(node_output_db.get(&dep_node_id)?.get(output_name)?.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                Some(dep_node_outputs)
-            })
-            .flatten()
-            .flatten()
-            .collect();
-
-        node_output_db.insert(
-            this_node_id,
-            self.node_outputs(&this_node_inputs, this_node_id)?,
-        );
-        Ok(())
+        ui.strong(trace.verdict.to_string());
     }
 
-    fn evaluate_path(path: &PredicateSet) -> anyhow::Result<Vec<NfObject>> {
-        use nf::{
-            schema::{Chain, NfObject::CmdObject, Rule},
-            stmt::{Match, Operator},
-        };
-        use nftables as nf;
-
-        let mut hasher = DefaultHasher::new();
-        path.hash(&mut hasher);
-        let mut chain_name = hasher.finish();
-        let mut current_subpath = vec![];
-        let mut is_incoming = true;
-        let mut objects = vec![];
-
-        for predicate in path {
-            let variant = predicate.variant.as_str();
-            match variant {
-                "core:source_address_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("saddr".into()),
-                        right: Expression::String(filter.to_string()),
-                        op: if rule == "match" {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:destination_address_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("daddr".into()),
-                        right: Expression::String(filter.to_string()),
-                        op: if rule == "match" {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:source_port_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("meta l4proto { tcp, udp } th sport".into()),
-                        right: Expression::String(filter.into()),
-                        op: if filter == rule {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:destination_port_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("meta l4proto { tcp, udp } th dport".into()),
-                        right: Expression::String(filter.to_string()),
-                        op: if rule == "match" {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:protocol_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("ip protocol".into()),
-                        right: Expression::String(filter.to_string()),
-                        op: if rule == "match" {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:interface_filter" => {
-                    let rule = predicate
-                        .params
-                        .get("rule")
-                        .ok_or(anyhow::anyhow!("Rule is required"))?;
-                    let filter = predicate
-                        .params
-                        .get("filter")
-                        .ok_or(anyhow::anyhow!("Filter is required"))?;
-                    let interface = if is_incoming { "iifname" } else { "oifname" };
-                    let match_rule = Match {
-                        left: Expression::String(interface.into()),
-                        right: Expression::String(filter.to_string()),
-                        op: if rule == "match" {
-                            Operator::EQ
-                        } else {
-                            Operator::NEQ
-                        },
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:family_splitter" => {
-                    let family = predicate
-                        .params
-                        .get("family")
-                        .ok_or(anyhow::anyhow!("Family is required"))?;
-                    let match_rule = Match {
-                        left: Expression::String("nfproto".into()),
-                        right: Expression::String(family.to_string()),
-                        op: Operator::EQ,
-                    };
-                    current_subpath.push(match_rule);
-                }
-                "core:file_ip_list" => {
-                    todo!("File IP list rules");
-                }
-                "core:source_nat" => {
-                    let hook = if is_incoming {
-                        nf::types::NfHook::Input
-                    } else {
-                        nf::types::NfHook::Output
-                    };
-                    let addr = predicate
-                        .params
-                        .get("addr")
-                        .ok_or(anyhow::anyhow!("Address is required"))?;
-                    let chain = Chain::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        Some(nf::types::NfChainType::NAT),
-                        Some(hook),
-                        None,
-                        None,
-                        Some(nf::types::NfChainPolicy::Accept),
-                    );
-                    let rule = Rule::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        current_subpath
-                            .iter()
-                            .map(|rule| nf::stmt::Statement::Match(rule.clone()))
-                            .chain(vec![nf::stmt::Statement::SNAT(Some(nf::stmt::NAT {
-                                addr: Some(Expression::String(addr.clone())),
-                                family: Some(NATFamily::IP),
-                                port: Some(
-                                    addr.clone()
-                                        .split(':')
-                                        .last()
-                                        .unwrap()
-                                        .to_string()
-                                        .parse()?,
-                                ),
-                                flags: None,
-                            }))])
-                            .collect(),
-                    );
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Chain(chain))));
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Rule(rule))));
-                    chain_name += 1;
-                    current_subpath.clear();
-                    current_subpath.push(Match {
-                        left: Expression::String("saddr".into()),
-                        right: Expression::String(addr.to_string()),
-                        op: Operator::EQ,
-                    });
-                }
-                "core:destination_nat" => {
-                    let hook = if is_incoming {
-                        nf::types::NfHook::Input
-                    } else {
-                        nf::types::NfHook::Output
-                    };
-                    let addr = predicate
-                        .params
-                        .get("addr")
-                        .ok_or(anyhow::anyhow!("Address is required"))?;
-                    let chain = Chain::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        Some(nf::types::NfChainType::NAT),
-                        Some(hook),
-                        None,
-                        None,
-                        Some(nf::types::NfChainPolicy::Accept),
-                    );
-                    let rule = Rule::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        current_subpath
-                            .iter()
-                            .map(|rule| nf::stmt::Statement::Match(rule.clone()))
-                            .chain(vec![nf::stmt::Statement::DNAT(Some(nf::stmt::NAT {
-                                addr: Some(Expression::String(addr.clone())),
-                                family: Some(NATFamily::IP),
-                                port: Some(
-                                    addr.clone()
-                                        .split(':')
-                                        .last()
-                                        .unwrap()
-                                        .to_string()
-                                        .parse()?,
-                                ),
-                                flags: None,
-                            }))])
-                            .collect(),
-                    );
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Chain(chain))));
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Rule(rule))));
-                    chain_name += 1;
-                    current_subpath.clear();
-                    current_subpath.push(Match {
-                        left: Expression::String("daddr".into()),
-                        right: Expression::String(addr.to_string()),
-                        op: Operator::EQ,
-                    });
-                }
-                "core:source" => {
-                    is_incoming = true;
-                }
-                "core:localhost" => {
-                    let chain = Chain::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        Some(nf::types::NfChainType::Filter),
-                        Some(nf::types::NfHook::Input),
-                        None,
-                        None,
-                        Some(nf::types::NfChainPolicy::Drop),
-                    );
-                    let rule = Rule::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        current_subpath
-                            .iter()
-                            .map(|rule| nf::stmt::Statement::Match(rule.clone()))
-                            .chain(vec![nf::stmt::Statement::Accept(Some(nf::stmt::Accept {}))])
-                            .collect(),
-                    );
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Chain(chain))));
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Rule(rule))));
-                    chain_name += 1;
-                    current_subpath.clear();
-                    is_incoming = false;
-                }
-                "core:drop" => {
-                    let hook = if is_incoming {
-                        nf::types::NfHook::Input
-                    } else {
-                        nf::types::NfHook::Output
-                    };
-                    let chain = Chain::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        Some(nf::types::NfChainType::Filter),
-                        Some(hook),
-                        None,
-                        None,
-                        Some(nf::types::NfChainPolicy::Accept),
-                    );
-                    let rule = Rule::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        current_subpath
-                            .iter()
-                            .map(|rule| nf::stmt::Statement::Match(rule.clone()))
-                            .chain(vec![nf::stmt::Statement::Drop(Some(nf::stmt::Drop {}))])
-                            .collect(),
-                    );
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Chain(chain))));
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Rule(rule))));
-                }
-                "core:accept" => {
-                    let chain = Chain::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        Some(nf::types::NfChainType::Filter),
-                        Some(nf::types::NfHook::Output),
-                        None,
-                        None,
-                        Some(nf::types::NfChainPolicy::Accept),
-                    );
-                    let rule = Rule::new(
-                        nf::types::NfFamily::INet,
-                        "netgraph".into(),
-                        chain_name.clone().to_string(),
-                        current_subpath
-                            .iter()
-                            .map(|rule| nf::stmt::Statement::Match(rule.clone()))
-                            .chain(vec![nf::stmt::Statement::Accept(Some(nf::stmt::Accept {}))])
-                            .collect(),
-                    );
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Chain(chain))));
-                    objects.push(CmdObject(NfCmd::Add(NfListObject::Rule(rule))));
-                }
-                _ => {
-                    return Err(anyhow::anyhow!("Unknown node type: {}", variant));
+    fn chain_settings_ui(&mut self, ui: &mut egui::Ui) {
+        use nftables::types::NfChainPolicy;
+
+        egui::Grid::new("chain_settings").show(ui, |ui| {
+            ui.label("Chain");
+            ui.label("Priority");
+            ui.label("Policy");
+            ui.end_row();
+            for base in BaseChain::ALL {
+                let mut settings = self.user_state.chains.get(base);
+                ui.label(base.name());
+                egui::DragValue::new(&mut settings.priority).ui(ui);
+                if base.is_filter() {
+                    egui::ComboBox::from_id_source(base.name())
+                        .selected_text(format!("{:?}", settings.policy))
+                        .show_ui(ui, |ui| {
+                            for policy in [NfChainPolicy::Accept, NfChainPolicy::Drop] {
+                                let text = format!("{policy:?}");
+                                ui.selectable_value(&mut settings.policy, policy, text);
+                            }
+                        });
                 }
-            };
-        }
-        Ok(objects)
-    }
-
-    fn break_loops(&self, output_id: OutputId) -> Option<()> {
-        let root_node_id = self.editor_state.graph.outputs.get(output_id)?.node;
-        let mut stack = vec![(0usize, self.get_connected_receiver_nodes(root_node_id))];
-
-        loop {
-            let traverse_deeper = {
-                let (subtree_index, subtree_nodes) = stack.last()?;
-                if let Some(node_id) = subtree_nodes.get(*subtree_index) {
-                    if *node_id == root_node_id {
-                        return Some(());
-                    }
-                    true
-                } else {
-                    false
+                ui.end_row();
+                if settings != self.user_state.chains.get(base) {
+                    self.user_state.chains.chains.insert(base, settings);
                 }
-            };
-
-            if traverse_deeper {
-                stack.last_mut()?.0 += 1;
-                let node_id = stack.last()?.1.get(stack.last()?.0 - 1)?;
-                stack.push((0, self.get_connected_receiver_nodes(*node_id)));
-            } else {
-                stack.pop();
             }
-        }
+        });
     }
 
     fn new_graph(&mut self) {
-        self.editor_state = GraphEditorState::default();
+        self.editor_state = NFEditorState::default();
         self.user_state = NFGraphState::default();
+        self.diagnostics.clear();
+        self.export_with_errors = false;
+        self.trace = None;
+        self.import_report.clear();
         self.all_kinds = NodeTemplateIter::new(Vec::new());
 
         for node_template in [NFNodeData::Source, NFNodeData::Localhost] {
@@ -879,6 +532,9 @@ impl App {
                 node_template.user_data(&mut self.user_state),
                 |graph, node_id| node_template.build_node(graph, &mut self.user_state, node_id),
             );
+            if node_template.is_source() {
+                self.source_node = node;
+            }
             self.editor_state
                 .node_positions
                 .insert(node, Pos2::default());
@@ -887,7 +543,6 @@ impl App {
     }
 
     fn save_node_graph(&self) -> anyhow::Result<()> {
-        use serde_json::to_value;
         let path = if let Some(path) = &self.current_graph_path {
             path.clone()
         } else if let Some(file) = rfd::FileDialog::new().pick_folder() {
@@ -898,61 +553,67 @@ impl App {
         } else {
             return Ok(());
         };
-        let source_node = to_value(self.source_node)
-            .or(Err(anyhow::anyhow!("source node is not serializable")))?;
-        let editor_state = to_value(&self.editor_state)
-            .or(Err(anyhow::anyhow!("editor state is not serializable")))?;
-        let plugins = to_value(&self.user_state.plugins)
-            .or(Err(anyhow::anyhow!("plugins is not serializable")))?;
-
-        let mut map = Map::new();
-        map.insert("source_node".to_string(), source_node);
-        map.insert("editor_state".to_string(), editor_state);
-        map.insert("plugins".to_string(), plugins);
-        let json = serde_json::to_string(&map).unwrap();
-
-        let graph_path = path
-            .clone()
-            .tap_mut(|s| s.as_mut_os_string().push("/graph.json"));
-        let plugins_path = path
-            .clone()
-            .tap_mut(|s| s.as_mut_os_string().push("/plugins"));
-        std::fs::write(graph_path, json).or(Err(anyhow::anyhow!("Cannot write graph file")))?;
-        std::fs::create_dir_all(plugins_path)?;
-        Ok(())
+        storage::save(
+            &path,
+            self.source_node,
+            &self.editor_state,
+            &self.user_state,
+        )
     }
 
     fn load_node_graph(&mut self) -> anyhow::Result<()> {
         let Some(path) = rfd::FileDialog::new().pick_folder() else {
             return Ok(());
         };
-        let graph_path = path
-            .clone()
-            .tap_mut(|s| s.as_mut_os_string().push("/graph.json"));
-        let json = std::fs::read_to_string(graph_path)
-            .or(Err(anyhow::anyhow!("Cannot read graph file")))?;
-        let map: Map<_, _> = serde_json::from_str(&json).or(Err(anyhow::anyhow!("")))?;
-        let source_node = serde_json::from_value(
-            map.get("source_node")
-                .cloned()
-                .ok_or(anyhow::anyhow!("incorrect file format"))?,
-        )?;
-        let editor_state = serde_json::from_value(
-            map.get("editor_state")
-                .cloned()
-                .ok_or(anyhow::anyhow!("incorrect file format"))?,
-        )?;
-        let user_state_plugins = serde_json::from_value(
-            map.get("plugins")
-                .cloned()
-                .ok_or(anyhow::anyhow!("incorrect file format"))?,
-        )?;
+        self.load_graph_dir(path)
+    }
+
+    /// Loads the graph saved in the directory `path`.
+    fn load_graph_dir(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        let saved = SavedGraph::load(&path)?;
         self.new_graph();
-        self.source_node = source_node;
-        self.user_state.plugins = user_state_plugins;
-        self.editor_state = editor_state;
+        self.source_node = saved.source_node;
+        self.user_state = saved.user_state;
+        self.editor_state = saved.editor_state;
         self.current_graph_path = Some(path);
         self.reload_all_kinds();
+        self.refresh_diagnostics();
+        Ok(())
+    }
+
+    fn import_ruleset(&mut self) -> anyhow::Result<()> {
+        let Some(path) = rfd::FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {e}", path.display()))?;
+        let ruleset = import::parse_ruleset(&text)?;
+
+        self.new_graph();
+        let localhost = self
+            .editor_state
+            .graph
+            .iter_nodes()
+            .find(|node_id| self.editor_state.graph[*node_id].user_data.is_localhost())
+            .ok_or(anyhow::anyhow!("Local Machine node not found"))?;
+        self.import_report = import::build_graph(
+            &ruleset,
+            &mut self.editor_state.graph,
+            &mut self.user_state,
+            self.source_node,
+            localhost,
+        );
+        for (node_id, pos) in import::layout(&self.editor_state.graph) {
+            self.editor_state.node_positions.insert(node_id, pos.into());
+            if !self.editor_state.node_order.contains(&node_id) {
+                self.editor_state.node_order.push(node_id);
+            }
+        }
+        let connections: Vec<_> = self.editor_state.graph.iter_connections().collect();
+        for (input_id, output_id) in connections {
+            topology::propagate_data_types(&mut self.editor_state.graph, input_id, output_id);
+        }
+        self.refresh_diagnostics();
         Ok(())
     }
 
@@ -988,7 +649,7 @@ impl App {
             return Err(anyhow::anyhow!("Plugin script not found"));
         }
         let mut node_scripts = vec![];
-        for (node_id, _) in &plugin.nf {
+        for node_id in plugin.nf.keys() {
             let node_script = plugin_source_dir
                 .clone()
                 .tap_mut(|s| s.as_mut_os_string().push(format!("/{node_id}")));
@@ -1033,52 +694,228 @@ impl App {
         Ok(())
     }
 
-    fn export_configuration(&self) -> anyhow::Result<()> {
+    /// Lists the paths of the graph that fail to compile, as exporting it
+    /// would. A graph that cannot be compiled at all reports why on export.
+    fn refresh_diagnostics(&mut self) {
+        self.diagnostics = self
+            .evaluator()
+            .compile()
+            .map(|(_, _, diagnostics)| diagnostics)
+            .unwrap_or_default();
+    }
+
+    fn evaluator(&self) -> Evaluator<'_> {
+        Evaluator::new(
+            &self.editor_state.graph,
+            &self.user_state.chains,
+            self.current_graph_path.as_deref(),
+        )
+    }
+
+    fn export_configuration(&mut self) -> anyhow::Result<()> {
+        let (nft, routes) = self.checked_configuration()?;
         let Some(save_path) = rfd::FileDialog::new().pick_folder() else {
             return Ok(());
         };
-        if save_path.read_dir()?.next().is_some() {
-            return Err(anyhow::anyhow!("Directory is not empty"));
-        }
-        let nft_json_path = save_path
-            .clone()
-            .tap_mut(|s| s.as_mut_os_string().push("/nft.json"));
+        bundle::write_bundle(&save_path, &nft, &routes)
+    }
 
-        let mut node_output_db = NodeOutputDB::new();
-        for node_id in self.editor_state.graph.iter_nodes() {
-            self.recurse_node_outputs(node_id, &mut node_output_db)?;
+    /// Compiles the graph for export, failing while nodes are invalid or,
+    /// unless `export_with_errors` is set, while paths fail to compile.
+    /// Returns the ruleset as nftables JSON and the policy routing.
+    fn checked_configuration(&mut self) -> anyhow::Result<(String, Vec<bundle::Route>)> {
+        let invalid_nodes = self.invalid_nodes();
+        if !invalid_nodes.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Fix {} invalid node(s) before exporting",
+                invalid_nodes.len()
+            ));
+        }
+        let (nft, routes, diagnostics) = self.evaluator().compile()?;
+        self.diagnostics = diagnostics;
+        if !self.diagnostics.is_empty() && !self.export_with_errors {
+            return Err(anyhow::anyhow!(
+                "{} path(s) failed to compile, see Diagnostics",
+                self.diagnostics.len()
+            ));
         }
-        let nf_objects: Vec<NfObject> = self
-            .editor_state
-            .graph
-            .nodes
-            .iter()
-            .filter(|(_, node)| node.outputs.is_empty())
-            .filter_map(|(node_id, _)| Some(node_output_db.get(&node_id)?.get("terminal")?.clone()))
-            .flatten()
-            .filter_map(|path| Self::evaluate_path(&path).ok())
-            .flatten()
-            .collect();
-        let table = NfObject::CmdObject(NfCmd::Add(NfListObject::Table(
-            nftables::schema::Table::new(nftables::types::NfFamily::INet, "netgraph".into()),
-        )));
-        let nft = Nftables {
-            objects: [vec![table], nf_objects].concat(),
-        };
         let nft = serde_json::to_string_pretty(&nft)
             .ok()
             .ok_or(anyhow::anyhow!("rules serialization failed"))?;
-        std::fs::write(nft_json_path, nft)?;
-        Ok(())
+        Ok((nft, routes))
+    }
+}
+
+/// Edits the fields of a NAT node, without the address for Masquerade and
+/// Redirect.
+fn nat_target_ui(ui: &mut egui::Ui, target: &mut NatTarget, addr_label: Option<&str>) {
+    if let Some(addr_label) = addr_label {
+        ui.label(addr_label);
+        egui::TextEdit::singleline(&mut target.addr)
+            .hint_text("192.0.2.1 or 192.0.2.1-192.0.2.9")
+            .ui(ui);
     }
+    ui.label("Port:");
+    egui::TextEdit::singleline(&mut target.port)
+        .hint_text("keep")
+        .ui(ui);
+    ui.checkbox(&mut target.persistent, "Persistent");
+    ui.checkbox(&mut target.random, "Random port");
 }
 
-impl Hash for Predicate {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.variant.hash(state);
-        for (key, value) in &self.params {
-            key.hash(state);
-            value.hash(state);
+fn load_balance_ui(ui: &mut egui::Ui, target: &mut LoadBalanceTarget) {
+    ui.label("Backends:");
+    let mut removed = None;
+    egui::Grid::new("backends").show(ui, |ui| {
+        ui.label("Address");
+        ui.label("Weight");
+        ui.end_row();
+        for (index, backend) in target.backends.iter_mut().enumerate() {
+            egui::TextEdit::singleline(&mut backend.addr)
+                .hint_text("192.0.2.1")
+                .ui(ui);
+            egui::DragValue::new(&mut backend.weight)
+                .clamp_range(1..=1000)
+                .ui(ui);
+            if ui.button("Remove").clicked() {
+                removed = Some(index);
+            }
+            ui.end_row();
         }
+    });
+    if let Some(index) = removed {
+        target.backends.remove(index);
+    }
+    if ui.button("Add backend").clicked() {
+        target.backends.push(Backend::default());
     }
+    ui.label("Distribution:");
+    ui.radio_value(&mut target.hashed, false, "Round-robin");
+    ui.radio_value(&mut target.hashed, true, "Hash of the source address");
+    ui.label("Port:");
+    egui::TextEdit::singleline(&mut target.port)
+        .hint_text("keep")
+        .ui(ui);
+}
+
+fn tcp_flags_ui(ui: &mut egui::Ui, options: &mut TcpFlagsOptions) {
+    ui.horizontal_wrapped(|ui| {
+        for (name, flags, mask) in TcpFlagsOptions::PRESETS {
+            if ui.button(name).clicked() {
+                *options = TcpFlagsOptions::preset(flags, mask);
+            }
+        }
+    });
+    egui::Grid::new("tcp_flags").show(ui, |ui| {
+        ui.label("Flag");
+        ui.label("Examined");
+        ui.label("Set");
+        ui.end_row();
+        for flag in params::TCP_FLAGS {
+            ui.label(flag.to_uppercase());
+            let mut examined = options.mask.iter().any(|f| f == flag);
+            if ui.checkbox(&mut examined, "").changed() {
+                select_tcp_flag(&mut options.mask, flag, examined);
+                if !examined {
+                    select_tcp_flag(&mut options.flags, flag, false);
+                }
+            }
+            let mut set = options.flags.iter().any(|f| f == flag);
+            if ui.checkbox(&mut set, "").changed() {
+                select_tcp_flag(&mut options.flags, flag, set);
+                if set {
+                    select_tcp_flag(&mut options.mask, flag, true);
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
+
+fn time_ui(ui: &mut egui::Ui, options: &mut TimeOptions) {
+    ui.label("Hours and dates are in UTC.");
+    egui::Grid::new("time").show(ui, |ui| {
+        for (label, from, until, hint) in [
+            (
+                "Hours",
+                &mut options.hour_from,
+                &mut options.hour_until,
+                "08:00",
+            ),
+            (
+                "Dates",
+                &mut options.date_from,
+                &mut options.date_until,
+                "2024-12-24",
+            ),
+        ] {
+            ui.label(label);
+            egui::TextEdit::singleline(from).hint_text(hint).ui(ui);
+            ui.label("until");
+            egui::TextEdit::singleline(until).hint_text(hint).ui(ui);
+            ui.end_row();
+        }
+    });
+    ui.label("Days:");
+    ui.horizontal_wrapped(|ui| {
+        for day in params::WEEKDAYS {
+            let mut checked = options.days.iter().any(|d| d == day);
+            if ui.checkbox(&mut checked, &day[..3]).changed() {
+                options.days = params::WEEKDAYS
+                    .iter()
+                    .filter(|d| {
+                        if **d == day {
+                            checked
+                        } else {
+                            options.days.iter().any(|s| s == *d)
+                        }
+                    })
+                    .map(|d| d.to_string())
+                    .collect();
+            }
+        }
+    });
+    if let Some(warning) = options.warning() {
+        ui.colored_label(ui.visuals().warn_fg_color, warning);
+    }
+}
+
+fn icmp_types_ui(ui: &mut egui::Ui, types: &mut Vec<String>) {
+    ui.label("Match ICMP type:");
+    let ipv6_only = params::ICMPV6_TYPES
+        .into_iter()
+        .filter(|t| !params::ICMP_TYPES.contains(t));
+    for icmp_type in params::ICMP_TYPES.into_iter().chain(ipv6_only) {
+        let text = match (
+            params::ICMP_TYPES.contains(&icmp_type),
+            params::ICMPV6_TYPES.contains(&icmp_type),
+        ) {
+            (true, false) => format!("{icmp_type} (IPv4)"),
+            (false, true) => format!("{icmp_type} (IPv6)"),
+            _ => icmp_type.to_string(),
+        };
+        let mut checked = types.iter().any(|t| t == icmp_type);
+        if ui.checkbox(&mut checked, text).changed() {
+            if checked {
+                types.push(icmp_type.to_string());
+            } else {
+                types.retain(|t| t != icmp_type);
+            }
+        }
+    }
+}
+
+/// Adds or removes `flag`, keeping the flags in the order of their bits.
+fn select_tcp_flag(selected: &mut Vec<String>, flag: &str, on: bool) {
+    *selected = params::TCP_FLAGS
+        .iter()
+        .filter(|f| {
+            if **f == flag {
+                on
+            } else {
+                selected.iter().any(|s| s == *f)
+            }
+        })
+        .map(|f| f.to_string())
+        .collect();
 }
@@ -0,0 +1,379 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, ExitCode, Stdio};
+
+use netgraph_core::bundle::{self, Route};
+use netgraph_core::nf_graph::simulate::{self, PacketFields, Verdict};
+use netgraph_core::{Evaluator, SavedGraph};
+
+const USAGE: &str = "\
+Usage:
+    netgraph                                     open the editor
+    netgraph validate <graph>                    check nodes and compile every path
+    netgraph export [--allow-errors] <graph> <out>
+                                                 write the configuration bundle to <out>
+    netgraph diff <graph> <bundle>               compare the graph with an exported bundle
+    netgraph simulate [--iif <if>] [--oif <if>] [--ct-state <state>] [--time <time>]
+                      [--tcp-flags <flags>] [--icmp-type <type>]
+                      <graph> <protocol> <source> <destination>
+                                                 trace a packet through the graph
+    netgraph apply --dry-run <graph>             check the configuration with `nft -c`
+    netgraph apply <graph> <out>                 export the bundle to <out> and run its apply.sh
+
+<graph> is a directory a graph was saved to. <time> is a UTC date and time
+of day, e.g. `2024-12-24 18:00`. <flags> are the set TCP flags, comma
+separated, e.g. `syn,ack`.
+
+Exit status is 0 on success, 1 when validation fails, the configurations
+differ, the packet is not accepted or nft rejects the configuration, and 2
+on any other error.";
+
+/// Whether a command succeeded, for the exit status.
+enum Outcome {
+    Success,
+    Failure,
+}
+
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    iifname: String,
+    oifname: String,
+    ct_state: String,
+    tcp_flags: String,
+    icmp_type: String,
+    time: String,
+}
+
+/// Runs the subcommand in `args`, which do not include the program name.
+pub fn run(args: Vec<String>) -> ExitCode {
+    ExitCode::from(status(args))
+}
+
+/// Runs the subcommand in `args` and returns its exit status.
+fn status(args: Vec<String>) -> u8 {
+    let result = Args::parse(args).and_then(|args| {
+        let positional: Vec<_> = args.positional.iter().map(String::as_str).collect();
+        match positional.as_slice() {
+            ["validate", graph] => validate(graph),
+            ["export", graph, out] => export(graph, out, args.flag("--allow-errors")),
+            ["diff", graph, bundle] => diff(graph, bundle),
+            ["simulate", graph, protocol, source, destination] => simulate(
+                graph,
+                PacketFields {
+                    protocol: protocol.to_string(),
+                    source: source.to_string(),
+                    destination: destination.to_string(),
+                    iifname: args.iifname.clone(),
+                    oifname: args.oifname.clone(),
+                    ct_state: args.ct_state.clone(),
+                    tcp_flags: args.tcp_flags.clone(),
+                    icmp_type: args.icmp_type.clone(),
+                    time: args.time.clone(),
+                },
+            ),
+            ["apply", graph] if args.flag("--dry-run") => apply_dry_run(graph),
+            ["apply", graph, out] if !args.flag("--dry-run") => apply(graph, out),
+            ["help"] => {
+                println!("{USAGE}");
+                Ok(Outcome::Success)
+            }
+            _ => Err(anyhow::anyhow!("Unknown command\n\n{USAGE}")),
+        }
+    });
+    match result {
+        Ok(Outcome::Success) => 0,
+        Ok(Outcome::Failure) => 1,
+        Err(err) => {
+            eprintln!("netgraph: {err}");
+            2
+        }
+    }
+}
+
+impl Args {
+    fn parse(args: Vec<String>) -> anyhow::Result<Self> {
+        let mut parsed = Args {
+            positional: vec![],
+            flags: vec![],
+            iifname: String::new(),
+            oifname: String::new(),
+            ct_state: String::new(),
+            tcp_flags: String::new(),
+            icmp_type: String::new(),
+            time: String::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(anyhow::anyhow!("`{arg}` needs a value"));
+            match arg.as_str() {
+                "--iif" => parsed.iifname = value()?,
+                "--oif" => parsed.oifname = value()?,
+                "--ct-state" => parsed.ct_state = value()?,
+                "--time" => parsed.time = value()?,
+                "--tcp-flags" => parsed.tcp_flags = value()?,
+                "--icmp-type" => parsed.icmp_type = value()?,
+                "--allow-errors" | "--dry-run" => parsed.flags.push(arg),
+                "-h" | "--help" => parsed.positional = vec!["help".into()],
+                flag if flag.starts_with("--") => {
+                    return Err(anyhow::anyhow!("Unknown option `{flag}`"))
+                }
+                _ => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
+
+fn load(graph: &str) -> anyhow::Result<SavedGraph> {
+    SavedGraph::load(Path::new(graph))
+        .map_err(|e| anyhow::anyhow!("Cannot load graph from {graph}: {e}"))
+}
+
+/// Compiles the graph the way `Export configuration` does, printing what
+/// fails. Returns the ruleset as nftables JSON, unless nodes are invalid or,
+/// without `allow_errors`, paths fail to compile.
+fn configuration(graph: &str, allow_errors: bool) -> anyhow::Result<Option<(String, Vec<Route>)>> {
+    let saved = load(graph)?;
+    let evaluator = Evaluator::new(
+        &saved.editor_state.graph,
+        &saved.user_state.chains,
+        Some(Path::new(graph)),
+    );
+    let invalid_nodes = evaluator.invalid_nodes();
+    if !invalid_nodes.is_empty() {
+        for (node_id, err) in &invalid_nodes {
+            eprintln!("{}: {err}", evaluator.graph[*node_id].label);
+        }
+        eprintln!("{} invalid node(s)", invalid_nodes.len());
+        return Ok(None);
+    }
+    let (nft, routes, diagnostics) = evaluator.compile()?;
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    if !diagnostics.is_empty() && !allow_errors {
+        eprintln!("{} path(s) failed to compile", diagnostics.len());
+        return Ok(None);
+    }
+    Ok(Some((serde_json::to_string_pretty(&nft)?, routes)))
+}
+
+fn validate(graph: &str) -> anyhow::Result<Outcome> {
+    Ok(match configuration(graph, false)? {
+        Some(_) => {
+            println!("{graph}: ok");
+            Outcome::Success
+        }
+        None => Outcome::Failure,
+    })
+}
+
+fn export(graph: &str, out: &str, allow_errors: bool) -> anyhow::Result<Outcome> {
+    let Some((nft, routes)) = configuration(graph, allow_errors)? else {
+        return Ok(Outcome::Failure);
+    };
+    std::fs::create_dir_all(out).map_err(|e| anyhow::anyhow!("Cannot create {out}: {e}"))?;
+    bundle::write_bundle(Path::new(out), &nft, &routes)?;
+    println!("Configuration exported to {out}");
+    Ok(Outcome::Success)
+}
+
+/// Lists the nftables objects only one of the configurations has, `-` for
+/// the exported bundle and `+` for the graph.
+fn diff(graph: &str, bundle: &str) -> anyhow::Result<Outcome> {
+    let Some((nft, _)) = configuration(graph, false)? else {
+        return Ok(Outcome::Failure);
+    };
+    let bundle_path = Path::new(bundle).join("nft.json");
+    let exported = std::fs::read_to_string(&bundle_path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {e}", bundle_path.display()))?;
+    let objects = |json: &str| -> anyhow::Result<Vec<String>> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let objects = value["nftables"].as_array().ok_or(anyhow::anyhow!(
+            "{} is not an nftables ruleset",
+            bundle_path.display()
+        ))?;
+        Ok(objects.iter().map(|object| object.to_string()).collect())
+    };
+    let (old, new) = (objects(&exported)?, objects(&nft)?);
+    let removed: Vec<_> = old.iter().filter(|object| !new.contains(object)).collect();
+    let added: Vec<_> = new.iter().filter(|object| !old.contains(object)).collect();
+    for object in &removed {
+        println!("- {object}");
+    }
+    for object in &added {
+        println!("+ {object}");
+    }
+    Ok(if removed.is_empty() && added.is_empty() {
+        Outcome::Success
+    } else {
+        Outcome::Failure
+    })
+}
+
+fn simulate(graph: &str, fields: PacketFields) -> anyhow::Result<Outcome> {
+    let saved = load(graph)?;
+    let trace = simulate::trace(
+        &saved.editor_state.graph,
+        saved.source_node,
+        &saved.user_state.chains,
+        Some(Path::new(graph)),
+        fields.packet()?,
+    );
+    for step in &trace.steps {
+        if step.note.is_empty() {
+            println!("{}", step.label);
+        } else {
+            println!("{} → {}", step.label, step.note);
+        }
+    }
+    for rewrite in &trace.rewrites {
+        println!("{rewrite}");
+    }
+    if !trace.rewrites.is_empty() {
+        println!("Final packet: {}", trace.packet);
+    }
+    println!("{}", trace.verdict);
+    match trace.verdict {
+        Verdict::Accept | Verdict::Policy(_, nftables::types::NfChainPolicy::Accept) => {
+            Ok(Outcome::Success)
+        }
+        Verdict::Drop | Verdict::Reject(_) | Verdict::Policy(..) => Ok(Outcome::Failure),
+        Verdict::Error(err) => Err(anyhow::anyhow!("{err}")),
+    }
+}
+
+fn apply_dry_run(graph: &str) -> anyhow::Result<Outcome> {
+    let Some((nft, _)) = configuration(graph, false)? else {
+        return Ok(Outcome::Failure);
+    };
+    let mut child = Command::new("nft")
+        .args(["-c", "-j", "-f", "-"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Cannot run nft: {e}"))?;
+    child.stdin.take().unwrap().write_all(nft.as_bytes())?;
+    if !child.wait()?.success() {
+        return Ok(Outcome::Failure);
+    }
+    println!("nft accepts the configuration");
+    Ok(Outcome::Success)
+}
+
+fn apply(graph: &str, out: &str) -> anyhow::Result<Outcome> {
+    if let Outcome::Failure = export(graph, out, false)? {
+        return Ok(Outcome::Failure);
+    }
+    let status = Command::new(Path::new(out).join("apply.sh"))
+        .status()
+        .map_err(|e| anyhow::anyhow!("Cannot run apply.sh: {e}"))?;
+    Ok(if status.success() {
+        Outcome::Success
+    } else {
+        Outcome::Failure
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use netgraph_core::nf_graph::NFNodeData;
+
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../netgraph_core/tests/fixtures")
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Saves the `family_split` fixture into `dir` with IPv6 sources on its
+    /// IPv4 output, a path that cannot compile.
+    fn failing_graph(dir: &Path) -> String {
+        let mut saved = load(&fixture("family_split")).unwrap();
+        for (_, node) in saved.editor_state.graph.nodes.iter_mut() {
+            if let NFNodeData::SourceAddressFilter(filter) = &mut node.user_data {
+                *filter = "fd00::/8".into();
+            }
+        }
+        std::fs::create_dir_all(dir).unwrap();
+        saved.save(dir).unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_failing_path_blocks_export() {
+        let dir = std::env::temp_dir().join(format!("netgraph-cli-failing-{}", std::process::id()));
+        let graph = failing_graph(&dir);
+
+        let saved = load(&graph).unwrap();
+        let evaluator = Evaluator::new(
+            &saved.editor_state.graph,
+            &saved.user_state.chains,
+            Some(Path::new(&graph)),
+        );
+        assert!(evaluator.invalid_nodes().is_empty());
+        let (_, _, diagnostics) = evaluator.compile().unwrap();
+        // both IPv4 paths through the filter fail, each reported on its own
+        let paths: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.node_label, "Source Address Filter");
+                diagnostic.path.join(" → ")
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "Incoming Source → Family Splitter → Source Address Filter → Local Machine → Accept",
+                "Incoming Source → Family Splitter → Source Address Filter → Drop",
+            ]
+        );
+
+        assert!(configuration(&graph, false).unwrap().is_none());
+        assert!(configuration(&graph, true).unwrap().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_exit_status() {
+        let dir = std::env::temp_dir().join(format!("netgraph-cli-status-{}", std::process::id()));
+        let graph = fixture("family_split");
+        let failing = failing_graph(&dir.join("failing"));
+        let missing = dir.join("missing").to_string_lossy().into_owned();
+        let bundle = dir.join("bundle").to_string_lossy().into_owned();
+
+        assert_eq!(status(args(&["validate", &graph])), 0);
+        assert_eq!(status(args(&["validate", &failing])), 1);
+        assert_eq!(status(args(&["validate", &missing])), 2);
+
+        assert_eq!(status(args(&["export", &graph, &bundle])), 0);
+        assert_eq!(status(args(&["diff", &graph, &bundle])), 0);
+        assert_eq!(
+            status(args(&["diff", &fixture("input_filter"), &bundle])),
+            1
+        );
+        assert_eq!(status(args(&["diff", &failing, &bundle])), 1);
+        assert_eq!(status(args(&["diff", &graph, &missing])), 2);
+
+        let simulate =
+            |source, destination| status(args(&["simulate", &graph, "tcp", source, destination]));
+        assert_eq!(simulate("10.0.0.1:40000", "192.0.2.1:22"), 0);
+        assert_eq!(simulate("192.0.2.9:40000", "192.0.2.1:22"), 1);
+        assert_eq!(simulate("10.0.0.1:40000", "fd00::1"), 2);
+
+        assert_eq!(status(args(&["validate"])), 2);
+        assert_eq!(status(args(&["validate", "--unknown", &graph])), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +0,0 @@
-pub use data_type::{DataType, NFDirection, NFFamily};
-pub use graph_state::NFGraphState;
-pub use node_data::NFNodeData;
-pub use node_template_iter::NodeTemplateIter;
-pub use response::NodeResponse;
-pub use value_type::ValueType;
-
-mod data_type;
-mod graph_state;
-pub mod node_data;
-mod node_template;
-mod node_template_iter;
-mod response;
-mod value_type;
@@ -1,8 +1,15 @@
+use std::process::ExitCode;
+
 mod app;
 
-fn main() {
+fn main() -> ExitCode {
     use eframe::egui::Visuals;
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return app::cli::run(args);
+    }
+
     eframe::run_native(
         "NetGraph",
         eframe::NativeOptions::default(),
@@ -12,4 +19,5 @@ fn main() {
         }),
     )
     .expect("Failed to run native example");
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,139 @@
+use std::fmt::Write as _;
+use std::net::IpAddr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+struct BundleFile {
+    name: &'static str,
+    contents: &'static str,
+    executable: bool,
+}
+
+const STATIC_FILES: [BundleFile; 3] = [
+    BundleFile {
+        name: "apply.sh",
+        contents: include_str!("bundle/apply.sh"),
+        executable: true,
+    },
+    BundleFile {
+        name: "rollback.sh",
+        contents: include_str!("bundle/rollback.sh"),
+        executable: true,
+    },
+    BundleFile {
+        name: "README.md",
+        contents: include_str!("bundle/README.md"),
+        executable: false,
+    },
+];
+
+/// Policy routing of a Route Via node: packets carrying `mark` are routed
+/// by `table`, whose default route leaves through `gateway` and `device`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Route {
+    pub mark: u32,
+    pub table: u32,
+    pub gateway: Option<IpAddr>,
+    pub device: Option<String>,
+}
+
+/// `routes.sh`, which adds the routing rules and tables of `routes` when
+/// run with `up` and removes them with `down`. Without a gateway the family
+/// is not known, so both get the route.
+pub fn routes_script(routes: &[Route]) -> String {
+    let mut up = String::new();
+    let mut down = String::new();
+    for route in routes {
+        let families: &[&str] = match route.gateway {
+            Some(IpAddr::V4(_)) => &["-4"],
+            Some(IpAddr::V6(_)) => &["-6"],
+            None => &["-4", "-6"],
+        };
+        let mut target = String::new();
+        if let Some(gateway) = route.gateway {
+            target += &format!(" via {gateway}");
+        }
+        if let Some(device) = &route.device {
+            target += &format!(" dev {device}");
+        }
+        let (mark, table) = (route.mark, route.table);
+        for family in families {
+            let rule =
+                format!("ip {family} rule del fwmark {mark:#x} table {table} 2>/dev/null || true");
+            up += &format!("    {rule}\n");
+            up += &format!("    ip {family} rule add fwmark {mark:#x} table {table}\n");
+            up += &format!("    ip {family} route replace default{target} table {table}\n");
+            down += &format!("    {rule}\n");
+            down += &format!("    ip {family} route flush table {table} 2>/dev/null || true\n");
+        }
+    }
+    format!(
+        "#!/bin/sh\n\
+         # Adds the policy routing of the Route Via nodes, `down` removes it.\n\
+         set -eu\n\
+         \n\
+         case \"${{1:-up}}\" in\n\
+         up)\n{up}    ;;\n\
+         down)\n{down}    ;;\n\
+         *)\n    echo \"usage: $0 [up|down]\" >&2\n    exit 1\n    ;;\n\
+         esac\n"
+    )
+}
+
+/// Writes the compiled ruleset and the routing script of `routes` together
+/// with the scripts applying and rolling them back, plus a `SHA256SUMS`
+/// manifest covering all of them, into the empty directory `dir`.
+pub fn write_bundle(dir: &Path, nft_json: &str, routes: &[Route]) -> anyhow::Result<()> {
+    if dir.read_dir()?.next().is_some() {
+        return Err(anyhow::anyhow!("Directory is not empty"));
+    }
+    let mut manifest = String::new();
+    let mut write_file = |name: &str, contents: &str, executable: bool| -> anyhow::Result<()> {
+        let path = dir.join(name);
+        std::fs::write(&path, contents)
+            .map_err(|e| anyhow::anyhow!("Cannot write {}: {e}", path.display()))?;
+        if executable {
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        }
+        let digest = Sha256::digest(contents.as_bytes());
+        for byte in digest {
+            write!(manifest, "{byte:02x}")?;
+        }
+        writeln!(manifest, "  {name}")?;
+        Ok(())
+    };
+
+    write_file("nft.json", nft_json, false)?;
+    write_file("routes.sh", &routes_script(routes), true)?;
+    for file in &STATIC_FILES {
+        write_file(file.name, file.contents, file.executable)?;
+    }
+    std::fs::write(dir.join("SHA256SUMS"), manifest)
+        .map_err(|e| anyhow::anyhow!("Cannot write checksum manifest: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routes_script() {
+        let script = routes_script(&[Route {
+            mark: 2,
+            table: 100,
+            gateway: Some("192.0.2.1".parse().unwrap()),
+            device: Some("wan2".into()),
+        }]);
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(
+            "up)\n    ip -4 rule del fwmark 0x2 table 100 2>/dev/null || true\n    \
+             ip -4 rule add fwmark 0x2 table 100\n    \
+             ip -4 route replace default via 192.0.2.1 dev wan2 table 100\n    ;;\n"
+        ));
+        assert!(script.contains("    ip -4 route flush table 100 2>/dev/null || true\n"));
+        assert!(!script.contains("ip -6"));
+    }
+}
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Replaces the netgraph nftables table with the exported configuration.
+set -eu
+cd "$(dirname "$0")"
+
+if [ "$(id -u)" -ne 0 ]; then
+    echo "apply.sh must be run as root" >&2
+    exit 1
+fi
+
+sha256sum --quiet -c SHA256SUMS
+
+# the file is checked as a whole before anything is changed
+nft -c -j -f nft.json
+
+if [ -e rollback.nft ]; then
+    echo "keeping the ruleset captured by a previous run in rollback.nft"
+else
+    nft list ruleset > rollback.nft
+fi
+
+# nft.json adds, deletes and recreates the netgraph table in one
+# transaction, so the old rules are replaced atomically
+nft -j -f nft.json
+./routes.sh up
+echo "configuration applied, run rollback.sh to restore the previous ruleset"
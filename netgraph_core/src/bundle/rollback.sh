@@ -0,0 +1,27 @@
+#!/bin/sh
+# Restores the ruleset captured by apply.sh.
+set -eu
+cd "$(dirname "$0")"
+
+if [ "$(id -u)" -ne 0 ]; then
+    echo "rollback.sh must be run as root" >&2
+    exit 1
+fi
+
+if [ ! -e rollback.nft ]; then
+    echo "rollback.nft not found, apply.sh was not run from this directory" >&2
+    exit 1
+fi
+
+restore=$(mktemp)
+trap 'rm -f "$restore"' EXIT
+{
+    echo "flush ruleset"
+    cat rollback.nft
+} > "$restore"
+
+./routes.sh down
+# flushing and loading happen in one transaction
+nft -f "$restore"
+rm rollback.nft
+echo "previous ruleset restored"
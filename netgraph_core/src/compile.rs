@@ -0,0 +1,2229 @@
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
+
+use ipnetwork::IpNetwork;
+use nftables::expr::{
+    BinaryOperation, Expression, JHash, Map, Meta, MetaKey, NamedExpression, NgMode, Numgen,
+    Payload, PayloadField, Prefix, Range, SetItem, CT,
+};
+use nftables::stmt::{
+    JumpTarget, Limit, Log, LogLevel, Mangle, Match, Meter, NATFamily, NATFlag, Operator, Reject,
+    RejectType, Statement,
+};
+use nftables::types::{NfFamily, RejectCode};
+
+pub use layout::{layout, BaseChain, ChainSettings, CompiledRule};
+pub use ruleset::{
+    ExtExpression, ExtMatch, ExtStatement, LimitObject, Nat, Object, QuotaObject, RuleStatement,
+    Ruleset, RulesetObject,
+};
+
+use crate::ip_list::IpList;
+use crate::nf_graph::{params, NFDirection, NFFamily, TimeOptions};
+use crate::{Predicate, PredicateSet};
+
+mod layout;
+mod ruleset;
+
+pub const TABLE: &str = "netgraph";
+
+/// Reject codes of the `icmp` type, for IPv4 packets.
+const ICMP_CODES: [&str; 7] = [
+    "port-unreachable",
+    "admin-prohibited",
+    "host-unreachable",
+    "net-unreachable",
+    "prot-unreachable",
+    "net-prohibited",
+    "host-prohibited",
+];
+/// Reject codes of the `icmpv6` type.
+const ICMPV6_CODES: [&str; 4] = [
+    "port-unreachable",
+    "admin-prohibited",
+    "no-route",
+    "addr-unreachable",
+];
+/// Reject codes of the `icmpx` type, translated to the family of the packet.
+const ICMPX_CODES: [&str; 4] = [
+    "port-unreachable",
+    "admin-prohibited",
+    "host-unreachable",
+    "no-route",
+];
+
+enum NatKind {
+    Source,
+    Destination,
+    LoadBalance,
+    Masquerade,
+    Redirect,
+}
+
+struct PathCompiler<'a> {
+    ip_lists: &'a BTreeMap<String, IpList>,
+    direction: NFDirection,
+    family: NFFamily,
+    /// Matches of the current segment since the last split.
+    matches: Vec<RuleStatement>,
+    rules: Vec<CompiledRule>,
+    /// Identity of the node whose predicate is being applied.
+    node: String,
+    /// Set once no packet can follow the rest of the path.
+    unreachable: bool,
+    /// Set past the Router, where the routing decision is made.
+    routed: bool,
+    /// Set when the filter being applied sends the packets of other
+    /// protocols to its non-match output.
+    other_protocol: bool,
+    /// Log and counter rules of the current segment since the last split,
+    /// by the node they were produced by, waiting for the base chain the
+    /// segment ends in.
+    side_rules: Vec<(String, Vec<RuleStatement>)>,
+    /// Rate limits and quotas the current segment passes through.
+    splits: Vec<Split>,
+}
+
+/// A Rate Limit or Quota node splitting a segment into the packets within
+/// and over the limit. The limit is checked once, in a chain of its own
+/// which sends packets within the limit on to a second chain with `goto`
+/// and holds the rules for the other packets itself. So the rules of both
+/// outputs share one limit, whatever their order.
+#[derive(Clone)]
+struct Split {
+    node: String,
+    /// Matches before the split, ending with the limit.
+    matches: Vec<RuleStatement>,
+    within: bool,
+    side_rules: Vec<(String, Vec<RuleStatement>)>,
+}
+
+/// A path that cannot be compiled, with the index of the predicate at fault.
+#[derive(Debug)]
+pub struct PathError {
+    pub predicate: usize,
+    pub error: anyhow::Error,
+}
+
+/// The named limits and quotas of the Rate Limit and Quota nodes on the
+/// paths, which the rules of both outputs of a node refer to. Nodes with
+/// invalid parameters are left out, their paths fail to compile.
+pub fn limit_objects(paths: &[PredicateSet]) -> Vec<RulesetObject> {
+    let mut objects = BTreeMap::new();
+    for predicate in paths.iter().flatten() {
+        let param = |name: &str| predicate.params.get(name).map_or("", String::as_str);
+        let object = match predicate.variant.as_str() {
+            "core:rate_limit" if param("per_source") != "true" => {
+                let (Ok(rate), Ok(burst)) =
+                    (params::rate(param("rate")), params::burst(param("burst")))
+                else {
+                    continue;
+                };
+                Object::Limit(LimitObject {
+                    family: NfFamily::INet,
+                    table: TABLE.into(),
+                    name: format!("limit_{}", predicate.node),
+                    limit: limit(&rate, burst),
+                })
+            }
+            "core:quota" => {
+                let Ok((val, unit)) = params::quota(param("quota")) else {
+                    continue;
+                };
+                let scale = params::BYTE_UNITS.iter().position(|u| *u == unit);
+                Object::Quota(QuotaObject {
+                    family: NfFamily::INet,
+                    table: TABLE.into(),
+                    name: format!("quota_{}", predicate.node),
+                    bytes: u64::from(val) << (10 * scale.unwrap_or_default()),
+                })
+            }
+            _ => continue,
+        };
+        objects.insert(predicate.node.clone(), RulesetObject::Add { add: object });
+    }
+    objects.into_values().collect()
+}
+
+/// Compiles a path of predicates from the Source node to a terminal node
+/// into rules, one for each hook the path passes through.
+///
+/// The non-match output of a TCP Flags or ICMP Type Filter also takes
+/// packets of other protocols, which one rule cannot match along with a
+/// mismatching header field. So the path is compiled once for each way
+/// through these filters, and the rules the variants share are emitted once.
+pub fn evaluate_path(
+    path: &PredicateSet,
+    ip_lists: &BTreeMap<String, IpList>,
+) -> Result<Vec<CompiledRule>, PathError> {
+    let either: Vec<usize> = path
+        .iter()
+        .enumerate()
+        .filter(|(_, predicate)| {
+            matches!(
+                predicate.variant.as_str(),
+                "core:tcp_flags_filter" | "core:icmp_type_filter"
+            ) && predicate.params.get("rule").map(String::as_str) == Some("non-match")
+        })
+        .map(|(index, _)| index)
+        .collect();
+    let mut rules = vec![];
+    for variant in 0..1u32 << either.len() {
+        let other_protocol = |index: usize| {
+            either
+                .iter()
+                .position(|i| *i == index)
+                .is_some_and(|bit| variant & (1 << bit) != 0)
+        };
+        rules.extend(evaluate_variant(path, ip_lists, other_protocol)?);
+    }
+    Ok(rules)
+}
+
+/// Compiles one way through `path`, where `other_protocol` tells the
+/// filters whose non-match output is taken by packets of other protocols.
+fn evaluate_variant(
+    path: &PredicateSet,
+    ip_lists: &BTreeMap<String, IpList>,
+    other_protocol: impl Fn(usize) -> bool,
+) -> Result<Vec<CompiledRule>, PathError> {
+    let mut compiler = PathCompiler {
+        ip_lists,
+        direction: NFDirection::Incoming,
+        family: NFFamily::Inet,
+        matches: vec![],
+        rules: vec![],
+        node: String::new(),
+        unreachable: false,
+        routed: false,
+        other_protocol: false,
+        side_rules: vec![],
+        splits: vec![],
+    };
+    for (index, predicate) in path.iter().enumerate() {
+        compiler.other_protocol = other_protocol(index);
+        compiler.apply(predicate).map_err(|error| PathError {
+            predicate: index,
+            error,
+        })?;
+        if compiler.unreachable {
+            break;
+        }
+    }
+    Ok(compiler.rules)
+}
+
+impl PathCompiler<'_> {
+    fn apply(&mut self, predicate: &Predicate) -> anyhow::Result<()> {
+        self.node.clone_from(&predicate.node);
+        let variant = predicate.variant.as_str();
+        match variant {
+            "core:source" => {
+                self.direction = NFDirection::Incoming;
+            }
+            "core:router" => {
+                // forwarded packets never reach the input hook, so the
+                // incoming matches are carried over to the forward chain,
+                // and local packets keep to the input hook
+                self.routed = true;
+                if predicate.params.get("route").map(String::as_str) != Some("local") {
+                    self.direction = NFDirection::Forwarded;
+                }
+            }
+            "core:source_address_filter" => self.match_address(predicate, "saddr")?,
+            "core:destination_address_filter" => self.match_address(predicate, "daddr")?,
+            "core:source_port_filter" => self.match_port(predicate, "sport")?,
+            "core:destination_port_filter" => self.match_port(predicate, "dport")?,
+            "core:protocol_filter" => {
+                let protocols = params::protocols(param(predicate, "filter")?)?;
+                for (protocol, family) in [("icmp", NFFamily::IPv4), ("icmpv6", NFFamily::IPv6)] {
+                    if protocols.iter().any(|p| p == protocol) {
+                        self.check_family(family, format_args!("`{protocol}`"))?;
+                    }
+                }
+                let left = match self.family {
+                    NFFamily::IPv4 => payload("ip", "protocol"),
+                    NFFamily::IPv6 => payload("ip6", "nexthdr"),
+                    NFFamily::Inet => meta(MetaKey::L4proto),
+                };
+                let right = set_or_single(protocols.iter().map(|protocol| {
+                    protocol
+                        .parse::<u32>()
+                        .map_or_else(|_| Expression::String(protocol.clone()), Expression::Number)
+                }));
+                self.push_match(Match {
+                    left,
+                    right,
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:tcp_flags_filter" => {
+                let flags = params::tcp_flags(param(predicate, "flags")?)?;
+                let mask = params::tcp_flags(param(predicate, "mask")?)?;
+                let op = rule_operator(predicate)?;
+                if self.other_protocol {
+                    self.push_match(Match {
+                        left: meta(MetaKey::L4proto),
+                        right: Expression::String("tcp".into()),
+                        op: Operator::NEQ,
+                    });
+                    return Ok(());
+                }
+                self.push_match(Match {
+                    left: meta(MetaKey::L4proto),
+                    right: Expression::String("tcp".into()),
+                    op: Operator::EQ,
+                });
+                self.push_match(Match {
+                    left: Expression::BinaryOperation(BinaryOperation::AND(
+                        Box::new(payload("tcp", "flags")),
+                        Box::new(Expression::Number(mask)),
+                    )),
+                    right: Expression::Number(flags),
+                    op,
+                });
+            }
+            "core:icmp_type_filter" => self.match_icmp_type(predicate)?,
+            "core:interface_filter" => {
+                let interfaces = params::interfaces(param(predicate, "filter")?)?;
+                let key = if self.direction == NFDirection::Incoming {
+                    MetaKey::Iifname
+                } else {
+                    MetaKey::Oifname
+                };
+                self.push_match(Match {
+                    left: meta(key),
+                    right: set_or_single(interfaces.into_iter().map(Expression::String)),
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:ct_state_filter" => {
+                let states = params::ct_states(param(predicate, "filter")?)?;
+                self.push_match(Match {
+                    left: ct("state"),
+                    right: set_or_single(states.into_iter().map(Expression::String)),
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:family_splitter" => {
+                let family = param(predicate, "family")?;
+                let split_family = match family.as_str() {
+                    "ipv4" => NFFamily::IPv4,
+                    "ipv6" => NFFamily::IPv6,
+                    _ => return Err(anyhow::anyhow!("Unknown family: {family}")),
+                };
+                if self.family != NFFamily::Inet && self.family != split_family {
+                    // packets of the other family never take this output
+                    self.unreachable = true;
+                    return Ok(());
+                }
+                self.narrow_family(split_family, format_args!("The `{family}` output"))?;
+                self.push_match(Match {
+                    left: meta(MetaKey::Nfproto),
+                    right: Expression::String(family.clone()),
+                    op: Operator::EQ,
+                });
+            }
+            "core:file_ip_list" => {
+                let list_path = param(predicate, "path")?;
+                let ip_list = self
+                    .ip_lists
+                    .get(list_path)
+                    .ok_or(anyhow::anyhow!("IP List {list_path} was not loaded"))?;
+                self.narrow_family(ip_list.family, format_args!("IP List {list_path}"))?;
+                self.push_match(Match {
+                    left: payload(address_protocol(ip_list.family), "saddr"),
+                    right: Expression::String(format!("@{}", ip_list.name)),
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:source_nat" => self.nat(predicate, &NatKind::Source)?,
+            "core:destination_nat" => self.nat(predicate, &NatKind::Destination)?,
+            "core:load_balance_nat" => self.nat(predicate, &NatKind::LoadBalance)?,
+            "core:masquerade" => self.nat(predicate, &NatKind::Masquerade)?,
+            "core:redirect" => self.nat(predicate, &NatKind::Redirect)?,
+            "core:localhost" => {
+                self.push_rule(BaseChain::FilterInput, Statement::Accept(None));
+                self.direction = NFDirection::Outgoing;
+                self.routed = false;
+            }
+            "core:drop" => {
+                self.push_rule(filter_chain(self.direction), Statement::Drop(None));
+            }
+            "core:accept" => {
+                self.push_rule(filter_chain(self.direction), Statement::Accept(None));
+            }
+            "core:reject" => self.reject(predicate)?,
+            "core:socket_user_filter" | "core:socket_group_filter" => {
+                self.check_outgoing(predicate)?;
+                let (ids, key) = if variant == "core:socket_user_filter" {
+                    (params::users(param(predicate, "filter")?)?, MetaKey::Skuid)
+                } else {
+                    (params::groups(param(predicate, "filter")?)?, MetaKey::Skgid)
+                };
+                self.push_match(Match {
+                    left: meta(key),
+                    right: set_or_single(ids.into_iter().map(Expression::Number)),
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:cgroup_filter" => {
+                self.check_outgoing(predicate)?;
+                let (path, level) = params::cgroup(param(predicate, "filter")?)?;
+                // nft looks the path up when loading the ruleset, which
+                // fails if the cgroup does not exist yet
+                let cgroup = ExtMatch {
+                    left: ExtExpression::Socket {
+                        key: "cgroupv2".into(),
+                        level,
+                    },
+                    right: Expression::String(path),
+                    op: rule_operator(predicate)?,
+                };
+                self.matches.push(ExtStatement::Match(cgroup).into());
+            }
+            "core:time_filter" => self.match_time(predicate)?,
+            "core:mark_filter" => {
+                let mark = params::mark(param(predicate, "filter")?)?;
+                self.push_match(Match {
+                    left: mark_key(param(predicate, "key")?)?,
+                    right: Expression::Number(mark),
+                    op: rule_operator(predicate)?,
+                });
+            }
+            "core:set_mark" => {
+                let mangle = Statement::Mangle(Mangle {
+                    key: mark_key(param(predicate, "key")?)?,
+                    value: Expression::Number(params::mark(param(predicate, "mark")?)?),
+                });
+                // later nodes of the path see the mark, so it is set right
+                // away instead of at the end of the segment
+                let base = self.mark_chain()?;
+                let (rules, parent) = self.split_rules(base, false);
+                self.rules.extend(rules);
+                let statements = self
+                    .matches
+                    .iter()
+                    .cloned()
+                    .chain([mangle.into()])
+                    .collect();
+                self.rules
+                    .push(named_rule(base, parent, &self.node, statements));
+            }
+            "core:route_via" => {
+                let mark = params::mark(param(predicate, "mark")?)?;
+                if self.routed {
+                    return Err(anyhow::anyhow!(
+                        "Route Via must be placed before the Router"
+                    ));
+                }
+                let mangle = Statement::Mangle(Mangle {
+                    key: meta(MetaKey::Mark),
+                    value: Expression::Number(mark),
+                });
+                self.push_rule(self.mark_chain()?, mangle);
+            }
+            "core:rate_limit" => self.rate_limit(predicate)?,
+            "core:quota" => {
+                params::quota(param(predicate, "quota")?)?;
+                let within = over_limit(predicate, "under quota", "over quota")?;
+                self.split(
+                    Statement::QuotaRef(format!("quota_{}", self.node)).into(),
+                    within,
+                );
+            }
+            "core:log" => {
+                let mut statements = vec![];
+                let rate = param(predicate, "rate")?;
+                if !rate.is_empty() {
+                    statements.push(Statement::Limit(limit(&params::rate(rate)?, None)).into());
+                }
+                let mut log =
+                    Log::new(params::log_group(param(predicate, "group")?)?.map(u32::from));
+                let prefix = param(predicate, "prefix")?;
+                params::log_prefix(prefix)?;
+                log.prefix = (!prefix.is_empty()).then(|| prefix.clone());
+                if log.group.is_none() {
+                    let level = param(predicate, "level")?;
+                    log.level = Some(
+                        serde_json::from_value::<LogLevel>(serde_json::Value::String(
+                            level.clone(),
+                        ))
+                        .map_err(|_| anyhow::anyhow!("`{level}` is not a log level"))?,
+                    );
+                }
+                statements.push(Statement::Log(Some(log)).into());
+                self.side_rule(statements);
+            }
+            "core:counter" => {
+                let name = param(predicate, "name")?.trim();
+                params::object_name(name)?;
+                let counter = if name.is_empty() {
+                    Statement::Counter(None)
+                } else {
+                    Statement::CounterRef(name.to_string())
+                };
+                self.side_rule(vec![counter.into()]);
+            }
+            _ => return Err(anyhow::anyhow!("Unknown node type: {variant}")),
+        }
+        Ok(())
+    }
+
+    fn match_address(&mut self, predicate: &Predicate, field: &str) -> anyhow::Result<()> {
+        let networks = params::addresses(param(predicate, "filter")?)?;
+        let family = if networks.iter().all(IpNetwork::is_ipv4) {
+            NFFamily::IPv4
+        } else if networks.iter().all(IpNetwork::is_ipv6) {
+            NFFamily::IPv6
+        } else {
+            return Err(anyhow::anyhow!(
+                "IPv4 and IPv6 addresses cannot be mixed in one filter"
+            ));
+        };
+        // an `ip`/`ip6` payload match implies the family in an inet table,
+        // so packets of the other family leave through neither output
+        self.narrow_family(family, format_args!("`{}`", networks[0]))?;
+        self.push_match(Match {
+            left: payload(address_protocol(family), field),
+            right: set_or_single(networks.iter().map(address)),
+            op: rule_operator(predicate)?,
+        });
+        Ok(())
+    }
+
+    /// Fails if the path only carries packets of a family other than `family`.
+    fn check_family(&self, family: NFFamily, what: std::fmt::Arguments) -> anyhow::Result<()> {
+        if self.family == NFFamily::Inet || self.family == family {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "{what} is {}, but the path only carries {} packets",
+            family_name(family),
+            family_name(self.family)
+        ))
+    }
+
+    /// Restricts the rest of the path to `family`.
+    fn narrow_family(&mut self, family: NFFamily, what: std::fmt::Arguments) -> anyhow::Result<()> {
+        self.check_family(family, what)?;
+        self.family = family;
+        Ok(())
+    }
+
+    /// Matches ICMP types of the path family, whose numbers differ between
+    /// `icmp` and `icmpv6`.
+    fn match_icmp_type(&mut self, predicate: &Predicate) -> anyhow::Result<()> {
+        let types = params::icmp_types(param(predicate, "filter")?)?;
+        let ipv4_only = types
+            .iter()
+            .find(|t| !params::ICMPV6_TYPES.contains(&t.as_str()));
+        let ipv6_only = types
+            .iter()
+            .find(|t| !params::ICMP_TYPES.contains(&t.as_str()));
+        match (ipv4_only, ipv6_only) {
+            (Some(ipv4), Some(ipv6)) => {
+                return Err(anyhow::anyhow!(
+                    "`{ipv4}` is IPv4 and `{ipv6}` is IPv6, they cannot be mixed in one filter"
+                ))
+            }
+            (Some(ipv4), None) => self.narrow_family(NFFamily::IPv4, format_args!("`{ipv4}`"))?,
+            (None, Some(ipv6)) => self.narrow_family(NFFamily::IPv6, format_args!("`{ipv6}`"))?,
+            (None, None) => {}
+        }
+        let protocol = match self.family {
+            NFFamily::IPv4 => "icmp",
+            NFFamily::IPv6 => "icmpv6",
+            NFFamily::Inet => {
+                return Err(anyhow::anyhow!(
+                    "ICMP types are numbered differently in IPv4 and IPv6, \
+                     place the ICMP Type Filter after a Family Splitter"
+                ))
+            }
+        };
+        let op = rule_operator(predicate)?;
+        if self.other_protocol {
+            self.push_match(Match {
+                left: meta(MetaKey::L4proto),
+                right: Expression::String(protocol.into()),
+                op: Operator::NEQ,
+            });
+            return Ok(());
+        }
+        self.push_match(Match {
+            left: meta(MetaKey::L4proto),
+            right: Expression::String(protocol.into()),
+            op: Operator::EQ,
+        });
+        self.push_match(Match {
+            left: payload(protocol, "type"),
+            right: set_or_single(types.into_iter().map(Expression::String)),
+            op,
+        });
+        Ok(())
+    }
+
+    fn match_port(&mut self, predicate: &Predicate, field: &str) -> anyhow::Result<()> {
+        let ports = params::ports(param(predicate, "filter")?)?;
+        self.push_match(Match {
+            left: meta(MetaKey::L4proto),
+            right: set_or_single(
+                params::PORT_PROTOCOLS
+                    .iter()
+                    .map(|protocol| Expression::String((*protocol).into())),
+            ),
+            op: Operator::EQ,
+        });
+        self.push_match(Match {
+            left: payload("th", field),
+            right: set_or_single(ports.into_iter().map(port_expression)),
+            op: rule_operator(predicate)?,
+        });
+        Ok(())
+    }
+
+    fn nat(&mut self, predicate: &Predicate, kind: &NatKind) -> anyhow::Result<()> {
+        let optional = |name: &str| predicate.params.get(name).map_or("", String::as_str);
+        let port = params::nat_port(optional("port"))?;
+        if port.is_some() && !self.matches_transport() {
+            return Err(anyhow::anyhow!(
+                "Translating the port needs a Protocol or Port Filter earlier on the path"
+            ));
+        }
+        let flags = match optional("flags") {
+            "" => None,
+            "persistent" => Some(HashSet::from([NATFlag::Persistent])),
+            "random" => Some(HashSet::from([NATFlag::Random])),
+            flags => return Err(anyhow::anyhow!("Unknown NAT flags: {flags}")),
+        };
+        let mut nat = Nat {
+            addr: None,
+            family: None,
+            port: port.map(port_expression),
+            flags,
+        };
+        let mut translated = None;
+        if matches!(kind, NatKind::Source | NatKind::Destination) {
+            let (first, last) = params::nat_address(param(predicate, "addr")?)?;
+            let (family, path_family) = match first {
+                IpAddr::V4(_) => (NATFamily::IP, NFFamily::IPv4),
+                IpAddr::V6(_) => (NATFamily::IP6, NFFamily::IPv6),
+            };
+            self.check_family(path_family, format_args!("`{first}`"))?;
+            let addr = match last {
+                Some(last) => Expression::Range(Range {
+                    range: vec![
+                        Expression::String(first.to_string()),
+                        Expression::String(last.to_string()),
+                    ],
+                }),
+                None => Expression::String(first.to_string()),
+            };
+            nat.addr = Some(addr.clone());
+            nat.family = Some(family);
+            translated = Some((path_family, addr));
+        } else if matches!(kind, NatKind::LoadBalance) {
+            let backends = params::backends(param(predicate, "backends")?)?;
+            let first = backends[0].0;
+            let (family, path_family) = match first {
+                IpAddr::V4(_) => (NATFamily::IP, NFFamily::IPv4),
+                IpAddr::V6(_) => (NATFamily::IP6, NFFamily::IPv6),
+            };
+            self.check_family(path_family, format_args!("`{first}`"))?;
+            nat.addr = Some(load_balance_map(
+                &backends,
+                param(predicate, "distribution")?,
+                path_family,
+            )?);
+            nat.family = Some(family);
+            let addrs = backends
+                .iter()
+                .map(|(addr, _)| Expression::String(addr.to_string()));
+            translated = Some((path_family, set_or_single(addrs)));
+        }
+        let nat = (nat.addr.is_some() || nat.port.is_some() || nat.flags.is_some()).then_some(nat);
+
+        let name = match kind {
+            NatKind::Source => "Source NAT",
+            NatKind::Destination => "Destination NAT",
+            NatKind::LoadBalance => "Load Balance NAT",
+            NatKind::Masquerade => "Masquerade",
+            NatKind::Redirect => "Redirect",
+        };
+        let (base, statement) = match (kind, self.direction) {
+            (NatKind::Source, NFDirection::Incoming) => {
+                (BaseChain::NatInput, ExtStatement::Snat(nat))
+            }
+            (NatKind::Source, _) => (BaseChain::NatPostrouting, ExtStatement::Snat(nat)),
+            (NatKind::Masquerade, NFDirection::Incoming) => {
+                return Err(anyhow::anyhow!(
+                    "Masquerade must be placed after Local Machine or the Router"
+                ))
+            }
+            (NatKind::Masquerade, _) => (BaseChain::NatPostrouting, ExtStatement::Masquerade(nat)),
+            (NatKind::Destination | NatKind::LoadBalance | NatKind::Redirect, _) if self.routed => {
+                return Err(anyhow::anyhow!("{name} must be placed before the Router"))
+            }
+            (NatKind::Destination | NatKind::LoadBalance, NFDirection::Outgoing) => {
+                (BaseChain::NatOutput, ExtStatement::Dnat(nat))
+            }
+            (NatKind::Destination | NatKind::LoadBalance, _) => {
+                (BaseChain::NatPrerouting, ExtStatement::Dnat(nat))
+            }
+            (NatKind::Redirect, NFDirection::Outgoing) => {
+                (BaseChain::NatOutput, ExtStatement::Redirect(nat))
+            }
+            (NatKind::Redirect, _) => (BaseChain::NatPrerouting, ExtStatement::Redirect(nat)),
+        };
+
+        let (matches, splits) = (self.matches.clone(), self.splits.clone());
+        self.push_rule(base, statement);
+        self.matches = matches;
+        self.restore_splits(splits);
+        match kind {
+            // source NAT happens after filtering, so the filter rules of the
+            // rest of the path still see the original packet
+            NatKind::Source | NatKind::Masquerade => {
+                if let Some((path_family, _)) = translated {
+                    self.family = path_family;
+                }
+            }
+            // the packet keeps its source, so only the destination matches
+            // are replaced by the translated address and port
+            NatKind::Destination | NatKind::LoadBalance | NatKind::Redirect => {
+                let keep = |s: &RuleStatement| !matches!(s, RuleStatement::Nft(Statement::Match(m)) if matches_destination(&m.left));
+                self.matches.retain(keep);
+                for split in &mut self.splits {
+                    split.matches.retain(keep);
+                }
+                if let Some((path_family, addr)) = translated {
+                    self.family = path_family;
+                    self.push_match(Match {
+                        left: payload(address_protocol(path_family), "daddr"),
+                        right: addr,
+                        op: Operator::EQ,
+                    });
+                }
+                if let Some(port) = port {
+                    self.push_match(Match {
+                        left: payload("th", "dport"),
+                        right: port_expression(port),
+                        op: Operator::EQ,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the collected matches restrict the packets to protocols
+    /// carrying ports, which nft requires before translating a port.
+    fn matches_transport(&self) -> bool {
+        let is_port_protocol = |expression: &Expression| match expression {
+            Expression::String(protocol) => params::PORT_PROTOCOLS.contains(&protocol.as_str()),
+            Expression::Number(number) => params::PORT_PROTOCOLS
+                .iter()
+                .any(|protocol| params::protocol_number(protocol).map(u32::from) == Some(*number)),
+            _ => false,
+        };
+        let splits = self.splits.iter().flat_map(|split| &split.matches);
+        splits.chain(&self.matches).any(|statement| {
+            let RuleStatement::Nft(Statement::Match(m)) = statement else {
+                return false;
+            };
+            let protocol_match = m.left == meta(MetaKey::L4proto)
+                || m.left == payload("ip", "protocol")
+                || m.left == payload("ip6", "nexthdr");
+            protocol_match && m.op == Operator::EQ && match &m.right {
+                Expression::Named(NamedExpression::Set(items)) => items.iter().all(
+                    |item| matches!(item, SetItem::Element(element) if is_port_protocol(element)),
+                ),
+                right => is_port_protocol(right),
+            }
+        })
+    }
+
+    /// Matches the hours, days and dates of a Time Filter together, so the
+    /// `non-match` output gets the packets failing any of them. Numbers are
+    /// taken by nft as UTC, like the Time Filter.
+    fn match_time(&mut self, predicate: &Predicate) -> anyhow::Result<()> {
+        let options = TimeOptions {
+            hour_from: param(predicate, "hour_from")?.clone(),
+            hour_until: param(predicate, "hour_until")?.clone(),
+            days: param(predicate, "days")?
+                .split(',')
+                .map(str::trim)
+                .filter(|day| !day.is_empty())
+                .map(str::to_string)
+                .collect(),
+            date_from: param(predicate, "date_from")?.clone(),
+            date_until: param(predicate, "date_until")?.clone(),
+        };
+        let window = options.window()?;
+        let range = |first: u32, last: u32| {
+            Expression::Range(Range {
+                range: vec![Expression::Number(first), Expression::Number(last)],
+            })
+        };
+        let mut keys = vec![];
+        let mut values = vec![];
+        if let Some((from, until)) = window.hours {
+            keys.push("hour");
+            // `meta hour` counts seconds since midnight, hours crossing it
+            // are split in two
+            values.push(match (from < until, until) {
+                (true, _) => vec![range(from * 60, until * 60 - 1)],
+                (false, 0) => vec![range(from * 60, 24 * 60 * 60 - 1)],
+                (false, _) => vec![range(0, until * 60 - 1), range(from * 60, 24 * 60 * 60 - 1)],
+            });
+        }
+        if !window.days.is_empty() {
+            keys.push("day");
+            values.push(
+                window
+                    .days
+                    .iter()
+                    .map(|day| Expression::String(params::WEEKDAYS[*day].into()))
+                    .collect(),
+            );
+        }
+        if window.from.is_some() || window.until.is_some() {
+            keys.push("time");
+            // `meta time` takes seconds since 1970, as far as the numbers
+            // of nftables-rs reach
+            let seconds = |minutes: i64| (minutes * 60).clamp(0, i64::from(u32::MAX)) as u32;
+            let from = window.from.map_or(0, seconds);
+            let until = window
+                .until
+                .map_or(u32::MAX, |until| seconds(until).saturating_sub(1));
+            values.push(vec![range(from, until)]);
+        }
+        let mut left: Vec<ExtExpression> = keys
+            .into_iter()
+            .map(|key| ExtExpression::Meta { key: key.into() })
+            .collect();
+        let (left, right) = if left.len() == 1 {
+            (left.remove(0), set_or_single(values.remove(0)))
+        } else {
+            // every combination of the values, as elements of a set of
+            // concatenations
+            let elements = values.into_iter().fold(vec![vec![]], |elements, values| {
+                elements
+                    .iter()
+                    .flat_map(|element: &Vec<Expression>| {
+                        values
+                            .iter()
+                            .map(|value| [element.clone(), vec![value.clone()]].concat())
+                    })
+                    .collect()
+            });
+            let elements = elements.into_iter().map(|element| {
+                SetItem::Element(Expression::Named(NamedExpression::Concat(element)))
+            });
+            (
+                ExtExpression::Concat(left),
+                Expression::Named(NamedExpression::Set(elements.collect())),
+            )
+        };
+        let time = ExtMatch {
+            left,
+            right,
+            op: rule_operator(predicate)?,
+        };
+        self.matches.push(ExtStatement::Match(time).into());
+        Ok(())
+    }
+
+    fn rate_limit(&mut self, predicate: &Predicate) -> anyhow::Result<()> {
+        let rate = params::rate(param(predicate, "rate")?)?;
+        let burst = params::burst(param(predicate, "burst")?)?;
+        let within = over_limit(predicate, "within limit", "over limit")?;
+        if param(predicate, "per_source")? != "true" {
+            self.split(
+                ExtStatement::Limit(format!("limit_{}", self.node)).into(),
+                within,
+            );
+            return Ok(());
+        }
+        // the limits of the sources live in the elements of a dynamic set,
+        // which every rule naming the meter shares
+        let protocol = match self.family {
+            NFFamily::Inet => {
+                return Err(anyhow::anyhow!(
+                    "A per-source limit needs a single family, add a Family Splitter before it"
+                ))
+            }
+            family => address_protocol(family),
+        };
+        let meter = Statement::Meter(Meter {
+            name: format!("limit_{}_{protocol}", self.node),
+            key: payload(protocol, "saddr"),
+            stmt: Box::new(Statement::Limit(limit(&rate, burst))),
+        });
+        self.split(meter.into(), within);
+        Ok(())
+    }
+
+    fn reject(&mut self, predicate: &Predicate) -> anyhow::Result<()> {
+        let reason = params::reject_reason(param(predicate, "reason")?)?;
+        let base = filter_chain(self.direction);
+        if reason != "tcp reset" {
+            let statement = self.icmp_reject(&reason)?;
+            self.push_rule(base, statement);
+            return Ok(());
+        }
+        // only TCP connections can be reset, packets of other protocols get
+        // the ICMP error nft replies with by default
+        let (matches, splits) = (self.matches.clone(), self.splits.clone());
+        self.push_match(Match {
+            left: meta(MetaKey::L4proto),
+            right: Expression::String("tcp".into()),
+            op: Operator::EQ,
+        });
+        let reset = Reject::new(Some(RejectType::TCPReset), None);
+        self.push_rule(base, Statement::Reject(Some(reset)));
+        self.matches = matches;
+        // side rules were placed with the first rule already
+        self.restore_splits(splits);
+        let statement = self.icmp_reject("port-unreachable")?;
+        self.push_rule(base, statement);
+        Ok(())
+    }
+
+    /// A `reject` statement with the ICMP type of the path family, or
+    /// `icmpx` on paths carrying both families.
+    fn icmp_reject(&self, code: &str) -> anyhow::Result<Statement> {
+        let reject_type = match self.family {
+            NFFamily::IPv4 if ICMP_CODES.contains(&code) => RejectType::ICMP,
+            NFFamily::IPv6 if ICMPV6_CODES.contains(&code) => RejectType::ICMPv6,
+            _ if ICMPX_CODES.contains(&code) => RejectType::ICMPX,
+            family => {
+                let code_family = if ICMP_CODES.contains(&code) {
+                    NFFamily::IPv4
+                } else {
+                    NFFamily::IPv6
+                };
+                self.check_family(code_family, format_args!("`{code}`"))?;
+                return Err(anyhow::anyhow!(
+                    "`{code}` is {}, but the path carries {} packets, \
+                     place the Reject node after a Family Splitter",
+                    family_name(code_family),
+                    family_name(family)
+                ));
+            }
+        };
+        let code: RejectCode = serde_json::from_value(serde_json::Value::String(code.into()))
+            .map_err(|_| anyhow::anyhow!("`{code}` is not a reject code"))?;
+        Ok(Statement::Reject(Some(Reject::new(
+            Some(reject_type),
+            Some(code),
+        ))))
+    }
+
+    /// Fails unless the path carries packets sent by local processes, the
+    /// only ones with a socket to match.
+    fn check_outgoing(&self, predicate: &Predicate) -> anyhow::Result<()> {
+        if self.direction == NFDirection::Outgoing {
+            return Ok(());
+        }
+        Err(anyhow::anyhow!(
+            "{} only matches outgoing packets, place it after Local Machine",
+            match predicate.variant.as_str() {
+                "core:socket_user_filter" => "Socket User Filter",
+                "core:socket_group_filter" => "Socket Group Filter",
+                _ => "Cgroup Filter",
+            }
+        ))
+    }
+
+    /// The base chain marks are set in, so the routing decision sees them.
+    /// Packets past the Router are already routed, so their marks only
+    /// reach the nodes further on.
+    fn mark_chain(&self) -> anyhow::Result<BaseChain> {
+        Ok(match self.direction {
+            NFDirection::Incoming | NFDirection::Either if self.routed => BaseChain::FilterInput,
+            NFDirection::Incoming | NFDirection::Either => BaseChain::MarkPrerouting,
+            NFDirection::Outgoing => BaseChain::MarkOutput,
+            NFDirection::Forwarded => BaseChain::FilterForward,
+        })
+    }
+
+    fn push_match(&mut self, m: Match) {
+        self.matches.push(Statement::Match(m).into());
+    }
+
+    /// Adds a rule running `statements` for the packets reaching the
+    /// current node, without deciding their fate. It gets a chain of its
+    /// own, so a rate limit does not keep packets from the rest of the path,
+    /// and paths sharing the node up to here share the rule.
+    fn side_rule(&mut self, statements: Vec<RuleStatement>) {
+        let statements = self.matches.iter().cloned().chain(statements).collect();
+        self.side_rules.push((self.node.clone(), statements));
+    }
+
+    /// Splits the segment at a Rate Limit or Quota checked by `limit`.
+    /// Rules for packets within the limit no longer need the matches
+    /// before it, which the chain of the split checks.
+    fn split(&mut self, limit: RuleStatement, within: Option<bool>) {
+        let within = within.is_none();
+        let mut matches = if within {
+            std::mem::take(&mut self.matches)
+        } else {
+            self.matches.clone()
+        };
+        matches.push(limit);
+        self.splits.push(Split {
+            node: self.node.clone(),
+            matches,
+            within,
+            side_rules: std::mem::take(&mut self.side_rules),
+        });
+    }
+
+    /// Restores the splits of a segment that goes on after a rule in
+    /// another base chain, without the side rules placed with that rule.
+    fn restore_splits(&mut self, splits: Vec<Split>) {
+        self.splits = splits
+            .into_iter()
+            .map(|split| Split {
+                side_rules: vec![],
+                ..split
+            })
+            .collect();
+    }
+
+    /// The chains of the splits of the current segment in `base`, along
+    /// with their side rules if `side_rules` is set, and the chain the rules
+    /// after the last split go in.
+    fn split_rules(
+        &self,
+        base: BaseChain,
+        side_rules: bool,
+    ) -> (Vec<CompiledRule>, Option<String>) {
+        let mut rules = vec![];
+        let mut parent = None;
+        for split in &self.splits {
+            if side_rules {
+                for (node, statements) in &split.side_rules {
+                    rules.push(named_rule(base, parent.clone(), node, statements.clone()));
+                }
+            }
+            let mut dispatch = named_rule(base, parent, &split.node, split.matches.clone());
+            let within = format!("{}_within", dispatch.name);
+            dispatch.statements.push(
+                Statement::Goto(JumpTarget {
+                    target: within.clone(),
+                })
+                .into(),
+            );
+            parent = Some(if split.within {
+                within.clone()
+            } else {
+                dispatch.name.clone()
+            });
+            rules.push(dispatch);
+            rules.push(CompiledRule {
+                base,
+                parent: None,
+                name: within,
+                statements: vec![],
+            });
+        }
+        (rules, parent)
+    }
+
+    /// Ends the current segment of the path with a rule made of the matches
+    /// collected so far and `verdict`, then starts collecting matches anew.
+    /// Side rules of the segment are placed before it in the same base chain.
+    fn push_rule(&mut self, base: BaseChain, verdict: impl Into<RuleStatement>) {
+        let (rules, parent) = self.split_rules(base, true);
+        self.rules.extend(rules);
+        self.splits.clear();
+        for (node, statements) in std::mem::take(&mut self.side_rules) {
+            self.rules
+                .push(named_rule(base, parent.clone(), &node, statements));
+        }
+        let statements: Vec<RuleStatement> =
+            self.matches.drain(..).chain([verdict.into()]).collect();
+        self.rules
+            .push(named_rule(base, parent, &self.node, statements));
+    }
+}
+
+/// Whether `left` looks at the destination address or port of a packet.
+fn matches_destination(left: &Expression) -> bool {
+    matches!(
+        left,
+        Expression::Named(NamedExpression::Payload(Payload::PayloadField(PayloadField { field, .. })))
+            if field == "daddr" || field == "dport"
+    )
+}
+
+/// Names a rule after its base chain, the node ending the segment and a
+/// hash of its statements and parent chain, so it keeps its name across
+/// exports as long as neither the node nor the path leading to it changes.
+fn named_rule(
+    base: BaseChain,
+    parent: Option<String>,
+    node: &str,
+    statements: Vec<RuleStatement>,
+) -> CompiledRule {
+    let json = serde_json::to_string(&statements).unwrap_or_default();
+    let hashed = match &parent {
+        Some(parent) => format!("{parent}{json}"),
+        None => json,
+    };
+    let name = format!(
+        "{}_{node}_{:08x}",
+        base.name(),
+        stable_hash(hashed.as_bytes()) as u32
+    );
+    CompiledRule {
+        base,
+        parent,
+        name,
+        statements,
+    }
+}
+
+fn address_protocol(family: NFFamily) -> &'static str {
+    if family == NFFamily::IPv6 {
+        "ip6"
+    } else {
+        "ip"
+    }
+}
+
+fn family_name(family: NFFamily) -> &'static str {
+    match family {
+        NFFamily::Inet => "IPv4 and IPv6",
+        NFFamily::IPv4 => "IPv4",
+        NFFamily::IPv6 => "IPv6",
+    }
+}
+
+/// FNV-1a, used instead of `DefaultHasher` whose output may change between
+/// Rust releases.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn param<'a>(predicate: &'a Predicate, name: &str) -> anyhow::Result<&'a String> {
+    predicate
+        .params
+        .get(name)
+        .ok_or(anyhow::anyhow!("{} is missing `{name}`", predicate.variant))
+}
+
+fn mark_key(key: &str) -> anyhow::Result<Expression> {
+    match key {
+        "meta" => Ok(meta(MetaKey::Mark)),
+        "ct" => Ok(ct("mark")),
+        _ => Err(anyhow::anyhow!("Unknown mark: {key}")),
+    }
+}
+
+/// Maps consecutive slots of a number generator or a hash of the source
+/// address to the backends, as many slots per backend as its weight.
+fn load_balance_map(
+    backends: &[(IpAddr, u32)],
+    distribution: &str,
+    family: NFFamily,
+) -> anyhow::Result<Expression> {
+    let slots = backends.iter().map(|(_, weight)| weight).sum();
+    let key = match distribution {
+        "round-robin" => Expression::Named(NamedExpression::Numgen(Numgen {
+            mode: NgMode::Inc,
+            ng_mod: slots,
+            offset: None,
+        })),
+        "hash" => Expression::Named(NamedExpression::JHash(JHash {
+            hash_mod: slots,
+            offset: None,
+            expr: Box::new(payload(address_protocol(family), "saddr")),
+            seed: None,
+        })),
+        _ => return Err(anyhow::anyhow!("Unknown distribution: {distribution}")),
+    };
+    let mut first = 0;
+    let mut mappings = vec![];
+    for (addr, weight) in backends {
+        let last = first + weight - 1;
+        let slot = if first == last {
+            Expression::Number(first)
+        } else {
+            Expression::Range(Range {
+                range: vec![Expression::Number(first), Expression::Number(last)],
+            })
+        };
+        mappings.push(SetItem::Mapping(slot, Expression::String(addr.to_string())));
+        first = last + 1;
+    }
+    Ok(Expression::Named(NamedExpression::Map(Box::new(Map {
+        key,
+        data: Expression::Named(NamedExpression::Set(mappings)),
+    }))))
+}
+
+/// A `limit` statement, matching packets within the rate.
+fn limit(rate: &params::Rate, burst: Option<u32>) -> Limit {
+    Limit {
+        rate: rate.count,
+        rate_unit: rate.unit.clone(),
+        per: Some(rate.per.clone()),
+        burst,
+        burst_unit: burst.and(rate.unit.clone()),
+        inv: None,
+    }
+}
+
+/// `None` for the output of packets within a limit or quota, named in
+/// `rule`, and `Some(true)` for the packets over it.
+fn over_limit(predicate: &Predicate, within: &str, over: &str) -> anyhow::Result<Option<bool>> {
+    match param(predicate, "rule")?.as_str() {
+        rule if rule == within => Ok(None),
+        rule if rule == over => Ok(Some(true)),
+        rule => Err(anyhow::anyhow!("Unknown rule: {rule}")),
+    }
+}
+
+fn rule_operator(predicate: &Predicate) -> anyhow::Result<Operator> {
+    match param(predicate, "rule")?.as_str() {
+        "match" => Ok(Operator::EQ),
+        "non-match" => Ok(Operator::NEQ),
+        rule => Err(anyhow::anyhow!("Unknown rule: {rule}")),
+    }
+}
+
+pub fn filter_chain(direction: NFDirection) -> BaseChain {
+    match direction {
+        NFDirection::Incoming | NFDirection::Either => BaseChain::FilterInput,
+        NFDirection::Outgoing => BaseChain::FilterOutput,
+        NFDirection::Forwarded => BaseChain::FilterForward,
+    }
+}
+
+pub fn payload(protocol: &str, field: &str) -> Expression {
+    Expression::Named(NamedExpression::Payload(Payload::PayloadField(
+        PayloadField {
+            protocol: protocol.into(),
+            field: field.into(),
+        },
+    )))
+}
+
+pub fn meta(key: MetaKey) -> Expression {
+    Expression::Named(NamedExpression::Meta(Meta { key }))
+}
+
+pub fn ct(key: &str) -> Expression {
+    Expression::Named(NamedExpression::CT(CT {
+        key: key.into(),
+        family: None,
+        dir: None,
+    }))
+}
+
+/// A single address, or a prefix expression for wider networks.
+pub fn address(network: &IpNetwork) -> Expression {
+    let host_prefix = if network.is_ipv4() { 32 } else { 128 };
+    if network.prefix() == host_prefix {
+        Expression::String(network.ip().to_string())
+    } else {
+        Expression::Named(NamedExpression::Prefix(Prefix {
+            addr: Box::new(Expression::String(network.network().to_string())),
+            len: u32::from(network.prefix()),
+        }))
+    }
+}
+
+/// A single port, or a range of ports.
+fn port_expression((first, last): (u16, u16)) -> Expression {
+    if first == last {
+        Expression::Number(u32::from(first))
+    } else {
+        Expression::Range(Range {
+            range: vec![
+                Expression::Number(u32::from(first)),
+                Expression::Number(u32::from(last)),
+            ],
+        })
+    }
+}
+
+/// The expression itself when there is only one, an anonymous set otherwise.
+fn set_or_single(items: impl IntoIterator<Item = Expression>) -> Expression {
+    let mut items: Vec<Expression> = items.into_iter().collect();
+    if items.len() == 1 {
+        items.remove(0)
+    } else {
+        Expression::Named(NamedExpression::Set(
+            items.into_iter().map(SetItem::Element).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use map_macro::btree_map;
+    use serde_json::json;
+
+    use super::*;
+
+    fn predicate(variant: &str, params: BTreeMap<String, String>) -> Predicate {
+        Predicate {
+            variant: variant.into(),
+            params,
+            node: format!("{}v1", variant.len()),
+        }
+    }
+
+    #[test]
+    fn test_port_filter_expressions() {
+        let path = vec![
+            predicate("core:source", btree_map! {}),
+            predicate(
+                "core:destination_port_filter",
+                btree_map! {
+                    "filter".into() => "22, 8000-8080".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate("core:localhost", btree_map! {}),
+        ];
+        let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+        assert_eq!(rules[0].base, BaseChain::FilterInput);
+        assert_eq!(rules[0].name, "input_14v1_6e40379f");
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements).unwrap(),
+            json!([
+                {"match": {
+                    "left": {"meta": {"key": "l4proto"}},
+                    "right": {"set": ["tcp", "udp", "udplite", "sctp", "dccp"]},
+                    "op": "==",
+                }},
+                {"match": {
+                    "left": {"payload": {"protocol": "th", "field": "dport"}},
+                    "right": {"set": [22, {"range": [8000, 8080]}]},
+                    "op": "==",
+                }},
+                {"accept": null},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ct_state_match() {
+        let path = vec![
+            predicate("core:source", btree_map! {}),
+            predicate(
+                "core:ct_state_filter",
+                btree_map! {
+                    "filter".into() => "established,related".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate("core:accept", btree_map! {}),
+        ];
+        let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[0]).unwrap(),
+            json!({"match": {
+                "left": {"ct": {"key": "state"}},
+                "right": {"set": ["established", "related"]},
+                "op": "==",
+            }})
+        );
+    }
+
+    #[test]
+    fn test_reject_type_follows_path() {
+        let reject_path = |family: Option<&str>, reason: &str| {
+            let mut path = vec![predicate("core:source", btree_map! {})];
+            if let Some(family) = family {
+                path.push(predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => family.into() },
+                ));
+            }
+            path.push(predicate(
+                "core:reject",
+                btree_map! { "reason".into() => reason.into() },
+            ));
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        let verdict =
+            |rule: &CompiledRule| serde_json::to_value(rule.statements.last().unwrap()).unwrap();
+
+        let rules = reject_path(None, "admin-prohibited").unwrap();
+        assert_eq!(
+            verdict(&rules[0]),
+            json!({"reject": {"type": "icmpx", "expr": "admin-prohibited"}})
+        );
+        let rules = reject_path(Some("ipv6"), "admin-prohibited").unwrap();
+        assert_eq!(
+            verdict(&rules[0]),
+            json!({"reject": {"type": "icmpv6", "expr": "admin-prohibited"}})
+        );
+        assert!(reject_path(None, "net-unreachable").is_err());
+        assert!(reject_path(Some("ipv4"), "addr-unreachable").is_err());
+
+        let rules = reject_path(Some("ipv4"), "tcp reset").unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1..]).unwrap(),
+            json!([
+                {"match": {"left": {"meta": {"key": "l4proto"}}, "right": "tcp", "op": "=="}},
+                {"reject": {"type": "tcp reset"}},
+            ])
+        );
+        assert_eq!(
+            verdict(&rules[1]),
+            json!({"reject": {"type": "icmp", "expr": "port-unreachable"}})
+        );
+    }
+
+    #[test]
+    fn test_redirect_and_masquerade() {
+        let nat_path = |variant: &str, port: &str| {
+            vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:protocol_filter",
+                    btree_map! {
+                        "filter".into() => "tcp".into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                predicate(
+                    variant,
+                    btree_map! {
+                        "addr".into() => String::new(),
+                        "port".into() => port.into(),
+                        "flags".into() => String::new(),
+                    },
+                ),
+                predicate("core:localhost", btree_map! {}),
+            ]
+        };
+        let rules = evaluate_path(&nat_path("core:redirect", "3128"), &BTreeMap::new()).unwrap();
+        assert_eq!(rules[0].base, BaseChain::NatPrerouting);
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap(),
+            json!({"redirect": {"port": 3128}})
+        );
+        assert_eq!(rules[1].base, BaseChain::FilterInput);
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements[1]).unwrap(),
+            json!({"match": {
+                "left": {"payload": {"protocol": "th", "field": "dport"}},
+                "right": 3128,
+                "op": "==",
+            }})
+        );
+
+        let rules =
+            evaluate_path(&nat_path("core:redirect", "8000-8010"), &BTreeMap::new()).unwrap();
+        let port_range = json!({"range": [8000, 8010]});
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap(),
+            json!({"redirect": {"port": port_range}})
+        );
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements[1]).unwrap()["match"]["right"],
+            port_range
+        );
+        let mut source_nat = nat_path("core:source_nat", "1024-2047");
+        source_nat[2]
+            .params
+            .insert("addr".into(), "192.0.2.1".into());
+        let rules = evaluate_path(&source_nat, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap(),
+            json!({"snat": {
+                "addr": "192.0.2.1",
+                "family": "ip",
+                "port": {"range": [1024, 2047]},
+            }})
+        );
+
+        let err = evaluate_path(&nat_path("core:masquerade", ""), &BTreeMap::new()).unwrap_err();
+        assert_eq!(
+            err.error.to_string(),
+            "Masquerade must be placed after Local Machine or the Router"
+        );
+        let mut without_protocol = nat_path("core:redirect", "3128");
+        without_protocol.remove(1);
+        assert!(evaluate_path(&without_protocol, &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_log_and_counter_side_rules() {
+        let path = vec![
+            predicate("core:source", btree_map! {}),
+            predicate(
+                "core:protocol_filter",
+                btree_map! {
+                    "filter".into() => "tcp".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate(
+                "core:log",
+                btree_map! {
+                    "prefix".into() => "ssh: ".into(),
+                    "level".into() => "info".into(),
+                    "group".into() => String::new(),
+                    "rate".into() => "10/minute".into(),
+                },
+            ),
+            predicate(
+                "core:counter",
+                btree_map! { "name".into() => String::new() },
+            ),
+            predicate("core:localhost", btree_map! {}),
+        ];
+        let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert!(rules.iter().all(|rule| rule.base == BaseChain::FilterInput));
+        assert!(rules[0].name.starts_with("input_8v1_"));
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1..]).unwrap(),
+            json!([
+                {"limit": {"rate": 10, "per": "minute"}},
+                {"log": {"prefix": "ssh: ", "level": "info"}},
+            ])
+        );
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements[1..]).unwrap(),
+            json!([{"counter": null}])
+        );
+        assert_eq!(
+            serde_json::to_value(rules[2].statements.last().unwrap()).unwrap(),
+            json!({"accept": null})
+        );
+    }
+
+    #[test]
+    fn test_marks_are_set_before_routing() {
+        let mark_path = |router: bool| {
+            let mut path = vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:set_mark",
+                    btree_map! {
+                        "mark".into() => "0x1".into(),
+                        "key".into() => "ct".into(),
+                    },
+                ),
+                predicate(
+                    "core:mark_filter",
+                    btree_map! {
+                        "filter".into() => "1".into(),
+                        "key".into() => "ct".into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+            ];
+            if router {
+                path.push(predicate("core:router", btree_map! {}));
+            }
+            path.push(predicate(
+                "core:route_via",
+                btree_map! {
+                    "mark".into() => "2".into(),
+                    "table".into() => "100".into(),
+                    "gateway".into() => "192.0.2.1".into(),
+                    "device".into() => String::new(),
+                },
+            ));
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        let rules = mark_path(false).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert!(rules
+            .iter()
+            .all(|rule| rule.base == BaseChain::MarkPrerouting));
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements).unwrap(),
+            json!([{"mangle": {"key": {"ct": {"key": "mark"}}, "value": 1}}])
+        );
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements).unwrap(),
+            json!([
+                {"match": {"left": {"ct": {"key": "mark"}}, "right": 1, "op": "=="}},
+                {"mangle": {"key": {"meta": {"key": "mark"}}, "value": 2}},
+            ])
+        );
+        assert_eq!(
+            mark_path(true).unwrap_err().error.to_string(),
+            "Route Via must be placed before the Router"
+        );
+    }
+
+    #[test]
+    fn test_tcp_flags_and_icmp_types() {
+        let rules = evaluate_path(
+            &vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:tcp_flags_filter",
+                    btree_map! {
+                        "flags".into() => "syn".into(),
+                        "mask".into() => "fin,syn,rst,ack".into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                predicate("core:drop", btree_map! {}),
+            ],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap(),
+            json!({"match": {
+                "left": {"&": [{"payload": {"protocol": "tcp", "field": "flags"}}, 23]},
+                "right": 2,
+                "op": "==",
+            }})
+        );
+
+        let icmp_path = |family: Option<&str>, types: &str| {
+            let mut path = vec![predicate("core:source", btree_map! {})];
+            if let Some(family) = family {
+                path.push(predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => family.into() },
+                ));
+            }
+            path.push(predicate(
+                "core:icmp_type_filter",
+                btree_map! {
+                    "filter".into() => types.into(),
+                    "rule".into() => "match".into(),
+                },
+            ));
+            path.push(predicate("core:accept", btree_map! {}));
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        let icmp_match = |rules: Vec<CompiledRule>| {
+            serde_json::to_value(&rules[0].statements[rules[0].statements.len() - 2]).unwrap()
+        };
+        assert_eq!(
+            icmp_match(icmp_path(Some("ipv6"), "echo-request").unwrap()),
+            json!({"match": {
+                "left": {"payload": {"protocol": "icmpv6", "field": "type"}},
+                "right": "echo-request",
+                "op": "==",
+            }})
+        );
+        // a type of one family narrows the path on its own
+        assert_eq!(
+            icmp_match(icmp_path(None, "echo-request, redirect").unwrap()),
+            json!({"match": {
+                "left": {"payload": {"protocol": "icmp", "field": "type"}},
+                "right": {"set": ["echo-request", "redirect"]},
+                "op": "==",
+            }})
+        );
+        assert!(icmp_path(None, "echo-request").is_err());
+        assert!(icmp_path(None, "redirect, packet-too-big").is_err());
+        assert!(icmp_path(Some("ipv6"), "redirect").is_err());
+    }
+
+    #[test]
+    fn test_non_match_takes_other_protocols() {
+        let l4proto = |protocol: &str, op: &str| {
+            json!({"match": {
+                "left": {"meta": {"key": "l4proto"}},
+                "right": protocol,
+                "op": op,
+            }})
+        };
+        let rules = evaluate_path(
+            &vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:tcp_flags_filter",
+                    btree_map! {
+                        "flags".into() => "syn".into(),
+                        "mask".into() => "fin,syn,rst,ack".into(),
+                        "rule".into() => "non-match".into(),
+                    },
+                ),
+                predicate("core:drop", btree_map! {}),
+            ],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap()["match"]["op"],
+            "!="
+        );
+        // a UDP packet has no flags to mismatch and is dropped by the second rule
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements).unwrap(),
+            json!([l4proto("tcp", "!="), {"drop": null}])
+        );
+
+        let rules = evaluate_path(
+            &vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => "ipv4".into() },
+                ),
+                predicate(
+                    "core:icmp_type_filter",
+                    btree_map! {
+                        "filter".into() => "echo-request".into(),
+                        "rule".into() => "non-match".into(),
+                    },
+                ),
+                predicate("core:accept", btree_map! {}),
+            ],
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        let statements = serde_json::to_value(&rules[1].statements).unwrap();
+        assert_eq!(
+            statements[statements.as_array().unwrap().len() - 2],
+            l4proto("icmp", "!=")
+        );
+    }
+
+    #[test]
+    fn test_time_filter() {
+        let time_path = |params: BTreeMap<String, String>| {
+            let mut params = params;
+            params.insert("rule".into(), "non-match".into());
+            for key in ["hour_from", "hour_until", "days", "date_from", "date_until"] {
+                params.entry(key.into()).or_default();
+            }
+            let path = vec![
+                predicate("core:source", btree_map! {}),
+                predicate("core:time_filter", params),
+                predicate("core:drop", btree_map! {}),
+            ];
+            let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+            serde_json::to_value(&rules[0].statements[0]).unwrap()
+        };
+        assert_eq!(
+            time_path(btree_map! {
+                "hour_from".into() => "22:00".into(),
+                "hour_until".into() => "06:30".into(),
+            }),
+            json!({"match": {
+                "left": {"meta": {"key": "hour"}},
+                "right": {"set": [
+                    {"range": [0, 23399]},
+                    {"range": [79200, 86399]},
+                ]},
+                "op": "!=",
+            }})
+        );
+        // every condition has to match, so they are checked together
+        assert_eq!(
+            time_path(btree_map! {
+                "days".into() => "Saturday,Sunday".into(),
+                "date_from".into() => "2024-12-24".into(),
+            }),
+            json!({"match": {
+                "left": {"concat": [
+                    {"meta": {"key": "day"}},
+                    {"meta": {"key": "time"}},
+                ]},
+                "right": {"set": [
+                    {"concat": ["Saturday", {"range": [1734998400, 4294967295u32]}]},
+                    {"concat": ["Sunday", {"range": [1734998400, 4294967295u32]}]},
+                ]},
+                "op": "!=",
+            }})
+        );
+    }
+
+    #[test]
+    fn test_socket_filters_need_outgoing_path() {
+        let socket_path = |localhost: bool, variant: &str, filter: &str| {
+            let mut path = vec![predicate("core:source", btree_map! {})];
+            if localhost {
+                path.push(predicate("core:localhost", btree_map! {}));
+            }
+            path.push(predicate(
+                variant,
+                btree_map! {
+                    "filter".into() => filter.into(),
+                    "rule".into() => "non-match".into(),
+                },
+            ));
+            path.push(predicate("core:drop", btree_map! {}));
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        let rules = socket_path(true, "core:socket_user_filter", "0, 1000").unwrap();
+        let drop = rules.last().unwrap();
+        assert_eq!(drop.base, BaseChain::FilterOutput);
+        assert_eq!(
+            serde_json::to_value(&drop.statements[0]).unwrap(),
+            json!({"match": {
+                "left": {"meta": {"key": "skuid"}},
+                "right": {"set": [0, 1000]},
+                "op": "!=",
+            }})
+        );
+        assert_eq!(
+            socket_path(false, "core:socket_group_filter", "0")
+                .unwrap_err()
+                .error
+                .to_string(),
+            "Socket Group Filter only matches outgoing packets, place it after Local Machine"
+        );
+        let rules = socket_path(true, "core:cgroup_filter", "/system.slice/nginx.service").unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules.last().unwrap().statements[0]).unwrap(),
+            json!({"match": {
+                "left": {"socket": {"key": "cgroupv2", "level": 2}},
+                "right": "system.slice/nginx.service",
+                "op": "!=",
+            }})
+        );
+    }
+
+    #[test]
+    fn test_destination_nat_keeps_source_matches() {
+        let path = vec![
+            predicate("core:source", btree_map! {}),
+            predicate(
+                "core:source_address_filter",
+                btree_map! {
+                    "filter".into() => "198.51.100.0/24".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate(
+                "core:destination_port_filter",
+                btree_map! {
+                    "filter".into() => "80".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate(
+                "core:destination_nat",
+                btree_map! {
+                    "addr".into() => "10.0.0.2".into(),
+                    "port".into() => "8080".into(),
+                    "flags".into() => String::new(),
+                },
+            ),
+            predicate("core:router", btree_map! {}),
+            predicate("core:accept", btree_map! {}),
+        ];
+        let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+        assert_eq!(rules[0].base, BaseChain::NatPrerouting);
+        assert_eq!(rules[1].base, BaseChain::FilterForward);
+        let statements = serde_json::to_value(&rules[1].statements).unwrap();
+        assert_eq!(
+            statements[0],
+            json!({"match": {
+                "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                "right": {"prefix": {"addr": "198.51.100.0", "len": 24}},
+                "op": "==",
+            }})
+        );
+        // the original destination port is replaced by the translated one
+        assert_eq!(
+            statements.as_array().unwrap()[2..],
+            [
+                json!({"match": {
+                    "left": {"payload": {"protocol": "ip", "field": "daddr"}},
+                    "right": "10.0.0.2",
+                    "op": "==",
+                }}),
+                json!({"match": {
+                    "left": {"payload": {"protocol": "th", "field": "dport"}},
+                    "right": 8080,
+                    "op": "==",
+                }}),
+                json!({"accept": null}),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_balance_map() {
+        let balance_path = |distribution: &str| {
+            vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:load_balance_nat",
+                    btree_map! {
+                        "backends".into() => "10.0.0.2 3, 10.0.0.3 1".into(),
+                        "distribution".into() => distribution.into(),
+                        "port".into() => String::new(),
+                    },
+                ),
+                predicate("core:router", btree_map! {}),
+                predicate("core:accept", btree_map! {}),
+            ]
+        };
+        let rules = evaluate_path(&balance_path("round-robin"), &BTreeMap::new()).unwrap();
+        assert_eq!(rules[0].base, BaseChain::NatPrerouting);
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[0]).unwrap(),
+            json!({"dnat": {
+                "addr": {"map": {
+                    "key": {"numgen": {"mode": "inc", "mod": 4}},
+                    "data": {"set": [
+                        [{"range": [0, 2]}, "10.0.0.2"],
+                        [3, "10.0.0.3"],
+                    ]},
+                }},
+                "family": "ip",
+            }})
+        );
+        assert_eq!(
+            serde_json::to_value(&rules[1].statements[0]).unwrap(),
+            json!({"match": {
+                "left": {"payload": {"protocol": "ip", "field": "daddr"}},
+                "right": {"set": ["10.0.0.2", "10.0.0.3"]},
+                "op": "==",
+            }})
+        );
+
+        let rules = evaluate_path(&balance_path("hash"), &BTreeMap::new()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[0]).unwrap()["dnat"]["addr"]["map"]["key"],
+            json!({"jhash": {
+                "mod": 4,
+                "expr": {"payload": {"protocol": "ip", "field": "saddr"}},
+            }})
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_and_quota() {
+        let limit_path = |rule: &str, per_source: bool, family: Option<&str>| {
+            let mut path = vec![predicate("core:source", btree_map! {})];
+            if let Some(family) = family {
+                path.push(predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => family.into() },
+                ));
+            }
+            path.push(predicate(
+                "core:rate_limit",
+                btree_map! {
+                    "rate".into() => "2 mbytes/second".into(),
+                    "burst".into() => "4".into(),
+                    "per_source".into() => per_source.to_string(),
+                    "rule".into() => rule.into(),
+                },
+            ));
+            path.push(predicate("core:drop", btree_map! {}));
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        // both outputs check the one limit of the node, in a chain of its
+        // own sending the packets within the limit on with goto
+        let within = limit_path("within limit", false, None).unwrap();
+        let over = limit_path("over limit", false, None).unwrap();
+        assert_eq!(within[..2], over[..2]);
+        let dispatch = &within[0];
+        assert_eq!(dispatch.parent, None);
+        assert_eq!(
+            serde_json::to_value(&dispatch.statements).unwrap(),
+            json!([
+                {"limit": "limit_15v1"},
+                {"goto": {"target": format!("{}_within", dispatch.name)}},
+            ])
+        );
+        assert_eq!(within[1].name, format!("{}_within", dispatch.name));
+        assert!(within[1].statements.is_empty());
+        assert_eq!(within[2].parent.as_ref(), Some(&within[1].name));
+        assert_eq!(over[2].parent.as_ref(), Some(&dispatch.name));
+        for rules in [&within, &over] {
+            assert_eq!(
+                serde_json::to_value(&rules[2].statements).unwrap(),
+                json!([{"drop": null}])
+            );
+        }
+        let paths = [vec![predicate(
+            "core:rate_limit",
+            btree_map! {
+                "rate".into() => "2 mbytes/second".into(),
+                "burst".into() => "4".into(),
+                "per_source".into() => "false".into(),
+                "rule".into() => "within limit".into(),
+            },
+        )]];
+        assert_eq!(
+            serde_json::to_value(limit_objects(&paths)).unwrap(),
+            json!([{"add": {"limit": {
+                "family": "inet",
+                "table": "netgraph",
+                "name": "limit_15v1",
+                "rate": 2,
+                "rate_unit": "mbytes",
+                "per": "second",
+                "burst": 4,
+                "burst_unit": "mbytes",
+            }}}])
+        );
+
+        assert!(limit_path("within limit", true, None).is_err());
+        let within = limit_path("within limit", true, Some("ipv6")).unwrap();
+        let over = limit_path("over limit", true, Some("ipv6")).unwrap();
+        assert_eq!(within[..2], over[..2]);
+        assert_eq!(
+            serde_json::to_value(&within[0].statements[1]).unwrap(),
+            json!({"meter": {
+                "name": "limit_15v1_ip6",
+                "key": {"payload": {"protocol": "ip6", "field": "saddr"}},
+                "stmt": {"limit": {
+                    "rate": 2,
+                    "rate_unit": "mbytes",
+                    "per": "second",
+                    "burst": 4,
+                    "burst_unit": "mbytes",
+                }},
+            }})
+        );
+
+        let quota_path = |rule: &str| {
+            let path = vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:quota",
+                    btree_map! {
+                        "quota".into() => "500 mbytes".into(),
+                        "rule".into() => rule.into(),
+                    },
+                ),
+                predicate("core:localhost", btree_map! {}),
+            ];
+            evaluate_path(&path, &BTreeMap::new())
+        };
+        let under = quota_path("under quota").unwrap();
+        let over = quota_path("over quota").unwrap();
+        assert_eq!(under[..2], over[..2]);
+        assert_eq!(
+            serde_json::to_value(&under[0].statements[0]).unwrap(),
+            json!({"quota": "quota_10v1"})
+        );
+        assert_eq!(under[2].parent.as_ref(), Some(&under[1].name));
+        assert_eq!(over[2].parent.as_ref(), Some(&over[0].name));
+        let paths = [vec![predicate(
+            "core:quota",
+            btree_map! {
+                "quota".into() => "500 mbytes".into(),
+                "rule".into() => "over quota".into(),
+            },
+        )]];
+        assert_eq!(
+            serde_json::to_value(limit_objects(&paths)).unwrap(),
+            json!([{"add": {"quota": {
+                "family": "inet",
+                "table": "netgraph",
+                "name": "quota_10v1",
+                "bytes": 500u64 << 20,
+            }}}])
+        );
+    }
+
+    #[test]
+    fn test_router_forwards() {
+        let path = vec![
+            predicate("core:source", btree_map! {}),
+            predicate(
+                "core:source_address_filter",
+                btree_map! {
+                    "filter".into() => "10.0.0.0/8".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate("core:router", btree_map! {}),
+            predicate(
+                "core:interface_filter",
+                btree_map! {
+                    "filter".into() => "wan0".into(),
+                    "rule".into() => "match".into(),
+                },
+            ),
+            predicate("core:accept", btree_map! {}),
+        ];
+        let rules = evaluate_path(&path, &BTreeMap::new()).unwrap();
+        // the incoming matches are carried over into the forward chain, and
+        // interfaces after the Router are the outgoing ones
+        let [rule] = rules.as_slice() else {
+            panic!("expected one rule, got {rules:?}");
+        };
+        assert_eq!(rule.base, BaseChain::FilterForward);
+        assert_eq!(
+            serde_json::to_value(&rule.statements).unwrap(),
+            json!([
+                {"match": {
+                    "left": {"payload": {"protocol": "ip", "field": "saddr"}},
+                    "right": {"prefix": {"addr": "10.0.0.0", "len": 8}},
+                    "op": "==",
+                }},
+                {"match": {
+                    "left": {"meta": {"key": "oifname"}},
+                    "right": "wan0",
+                    "op": "==",
+                }},
+                {"accept": null},
+            ])
+        );
+        let objects =
+            serde_json::to_value(layout(rules.clone(), &ChainSettings::default())).unwrap();
+        assert!(objects
+            .as_array()
+            .unwrap()
+            .contains(&json!({"add": {"chain": {
+                "family": "inet",
+                "table": "netgraph",
+                "name": "forward",
+                "type": "filter",
+                "hook": "forward",
+                "prio": 0,
+                "policy": "accept",
+            }}})));
+        assert!(objects
+            .as_array()
+            .unwrap()
+            .contains(&json!({"add": {"rule": {
+                "family": "inet",
+                "table": "netgraph",
+                "chain": "forward",
+                "expr": [{"jump": {"target": rule.name}}],
+            }}})));
+    }
+
+    #[test]
+    fn test_router_local_output() {
+        let local_path = |node: Predicate| {
+            vec![
+                predicate("core:source", btree_map! {}),
+                predicate("core:router", btree_map! {"route".into() => "local".into()}),
+                predicate(
+                    "core:interface_filter",
+                    btree_map! {
+                        "filter".into() => "lan0".into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                node,
+            ]
+        };
+        // local packets keep to the input chain, where interfaces are the
+        // incoming ones
+        let rules = evaluate_path(
+            &local_path(predicate("core:localhost", btree_map! {})),
+            &BTreeMap::new(),
+        )
+        .unwrap();
+        let [rule] = rules.as_slice() else {
+            panic!("expected one rule, got {rules:?}");
+        };
+        assert_eq!(rule.base, BaseChain::FilterInput);
+        assert_eq!(
+            serde_json::to_value(&rule.statements).unwrap(),
+            json!([
+                {"match": {
+                    "left": {"meta": {"key": "iifname"}},
+                    "right": "lan0",
+                    "op": "==",
+                }},
+                {"accept": null},
+            ])
+        );
+
+        // the routing decision is made, so it cannot be changed any more
+        let path = local_path(predicate(
+            "core:redirect",
+            btree_map! {"port".into() => String::new()},
+        ));
+        assert_eq!(
+            evaluate_path(&path, &BTreeMap::new())
+                .unwrap_err()
+                .error
+                .to_string(),
+            "Redirect must be placed before the Router"
+        );
+    }
+
+    #[test]
+    fn test_family_splitter_output_of_other_family() {
+        let split_path = |family: &str, filter: &str| {
+            vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:source_address_filter",
+                    btree_map! {
+                        "filter".into() => "2001:db8::/32".into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => family.into() },
+                ),
+                predicate(
+                    "core:destination_address_filter",
+                    btree_map! {
+                        "filter".into() => filter.into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                predicate("core:drop", btree_map! {}),
+            ]
+        };
+        let rules = evaluate_path(&split_path("ipv6", "2001:db8::1"), &BTreeMap::new()).unwrap();
+        assert_eq!(rules.len(), 1);
+        // no IPv6 packet takes the IPv4 output, so the path ends there
+        // without a rule, and without checking the nodes after it
+        let rules = evaluate_path(&split_path("ipv4", "10.0.0.1"), &BTreeMap::new()).unwrap();
+        assert!(rules.is_empty());
+        let rules = evaluate_path(&split_path("ipv4", "not an address"), &BTreeMap::new()).unwrap();
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn test_family_follows_path() {
+        let ipv6_path = |filter: &str| {
+            vec![
+                predicate("core:source", btree_map! {}),
+                predicate(
+                    "core:family_splitter",
+                    btree_map! { "family".into() => "ipv6".into() },
+                ),
+                predicate(
+                    "core:source_address_filter",
+                    btree_map! {
+                        "filter".into() => filter.into(),
+                        "rule".into() => "match".into(),
+                    },
+                ),
+                predicate("core:drop", btree_map! {}),
+            ]
+        };
+        let rules = evaluate_path(&ipv6_path("2001:db8::/32"), &BTreeMap::new()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&rules[0].statements[1]).unwrap(),
+            json!({"match": {
+                "left": {"payload": {"protocol": "ip6", "field": "saddr"}},
+                "right": {"prefix": {"addr": "2001:db8::", "len": 32}},
+                "op": "==",
+            }})
+        );
+
+        let err = evaluate_path(&ipv6_path("10.0.0.0/8"), &BTreeMap::new()).unwrap_err();
+        assert_eq!(err.predicate, 2);
+        assert_eq!(
+            err.error.to_string(),
+            "`10.0.0.0/8` is IPv4, but the path only carries IPv6 packets"
+        );
+    }
+}
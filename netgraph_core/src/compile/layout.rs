@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use nftables::schema::{Chain, NfCmd, NfListObject, NfObject};
+use nftables::stmt::{JumpTarget, Statement};
+use nftables::types::{NfChainPolicy, NfChainType, NfFamily, NfHook};
+use serde::{Deserialize, Serialize};
+
+use super::ruleset::{Object, Rule, RuleStatement, RulesetObject};
+use super::TABLE;
+
+/// The base chains of the netgraph table. Each one is attached to a hook
+/// and only jumps to the regular chains compiled from graph paths.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BaseChain {
+    /// Sets marks before the routing decision of incoming packets.
+    MarkPrerouting,
+    NatPrerouting,
+    FilterInput,
+    NatInput,
+    FilterForward,
+    /// Sets marks of outgoing packets, which are routed again if they change.
+    MarkOutput,
+    FilterOutput,
+    NatOutput,
+    NatPostrouting,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaseChainSettings {
+    pub priority: i32,
+    pub policy: NfChainPolicy,
+}
+
+/// User configurable priorities and default policies of the base chains.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainSettings {
+    pub chains: BTreeMap<BaseChain, BaseChainSettings>,
+}
+
+/// A rule compiled from a segment of a graph path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledRule {
+    pub base: BaseChain,
+    /// Chain jumping to the rule, the base chain if `None`.
+    pub parent: Option<String>,
+    /// Name of the regular chain holding the rule.
+    pub name: String,
+    /// Empty for a chain only reached by `goto`, which holds nothing but
+    /// the jumps to the rules under it.
+    pub statements: Vec<RuleStatement>,
+}
+
+impl BaseChain {
+    pub const ALL: [BaseChain; 9] = [
+        BaseChain::MarkPrerouting,
+        BaseChain::NatPrerouting,
+        BaseChain::FilterInput,
+        BaseChain::NatInput,
+        BaseChain::FilterForward,
+        BaseChain::MarkOutput,
+        BaseChain::FilterOutput,
+        BaseChain::NatOutput,
+        BaseChain::NatPostrouting,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BaseChain::MarkPrerouting => "mark_prerouting",
+            BaseChain::NatPrerouting => "nat_prerouting",
+            BaseChain::FilterInput => "input",
+            BaseChain::NatInput => "nat_input",
+            BaseChain::FilterForward => "forward",
+            BaseChain::MarkOutput => "mark_output",
+            BaseChain::FilterOutput => "output",
+            BaseChain::NatOutput => "nat_output",
+            BaseChain::NatPostrouting => "nat_postrouting",
+        }
+    }
+
+    /// The base chain attached to `hook` with `chain_type`, if any.
+    pub fn from_hook(chain_type: &NfChainType, hook: &NfHook) -> Option<BaseChain> {
+        BaseChain::ALL
+            .into_iter()
+            .find(|base| base.chain_type() == *chain_type && base.hook() == *hook)
+    }
+
+    pub fn is_filter(self) -> bool {
+        matches!(
+            self,
+            BaseChain::FilterInput | BaseChain::FilterForward | BaseChain::FilterOutput
+        )
+    }
+
+    fn chain_type(self) -> NfChainType {
+        match self {
+            BaseChain::MarkPrerouting => NfChainType::Filter,
+            BaseChain::MarkOutput => NfChainType::Route,
+            base if base.is_filter() => NfChainType::Filter,
+            _ => NfChainType::NAT,
+        }
+    }
+
+    fn hook(self) -> NfHook {
+        match self {
+            BaseChain::MarkPrerouting | BaseChain::NatPrerouting => NfHook::Prerouting,
+            BaseChain::FilterInput | BaseChain::NatInput => NfHook::Input,
+            BaseChain::FilterForward => NfHook::Forward,
+            BaseChain::MarkOutput | BaseChain::FilterOutput | BaseChain::NatOutput => {
+                NfHook::Output
+            }
+            BaseChain::NatPostrouting => NfHook::Postrouting,
+        }
+    }
+
+    /// Standard priorities from nft(8): `mangle` for mark chains, `filter`
+    /// for filter chains, `dstnat` and `srcnat` for NAT chains. Only incoming
+    /// traffic is denied by default.
+    pub fn default_settings(self) -> BaseChainSettings {
+        let priority = match self {
+            BaseChain::MarkPrerouting | BaseChain::MarkOutput => -150,
+            BaseChain::NatPrerouting | BaseChain::NatOutput => -100,
+            BaseChain::NatInput | BaseChain::NatPostrouting => 100,
+            _ => 0,
+        };
+        let policy = if self == BaseChain::FilterInput {
+            NfChainPolicy::Drop
+        } else {
+            NfChainPolicy::Accept
+        };
+        BaseChainSettings { priority, policy }
+    }
+}
+
+impl ChainSettings {
+    pub fn get(&self, base: BaseChain) -> BaseChainSettings {
+        self.chains
+            .get(&base)
+            .cloned()
+            .unwrap_or_else(|| base.default_settings())
+    }
+}
+
+/// Lays compiled rules out into chains. Every rule gets its own regular
+/// chain, which the base chain of its hook, or the parent chain of the
+/// rule, jumps to in path order. Rules sharing a name are emitted once. The
+/// filter base chains are always emitted so their policies apply, mark and
+/// NAT base chains only when some path needs them.
+pub fn layout(
+    rules: impl IntoIterator<Item = CompiledRule>,
+    settings: &ChainSettings,
+) -> Vec<RulesetObject> {
+    let mut chains: BTreeMap<BaseChain, Vec<CompiledRule>> = BaseChain::ALL
+        .into_iter()
+        .filter(|base| base.is_filter())
+        .map(|base| (base, vec![]))
+        .collect();
+    for rule in rules {
+        let base_rules = chains.entry(rule.base).or_default();
+        // outgoing segments are shared by every path reaching Localhost
+        if !base_rules.iter().any(|known| known.name == rule.name) {
+            base_rules.push(rule);
+        }
+    }
+
+    let add = |object| RulesetObject::Nft(NfObject::CmdObject(NfCmd::Add(object)));
+    let add_rule = |chain: String, expr| RulesetObject::Add {
+        add: Object::Rule(Rule {
+            family: NfFamily::INet,
+            table: TABLE.into(),
+            chain,
+            expr,
+        }),
+    };
+    let mut chain_objects = vec![];
+    let mut rule_objects = vec![];
+    for (base, base_rules) in chains {
+        let base_settings = settings.get(base);
+        chain_objects.push(add(NfListObject::Chain(Chain::new(
+            NfFamily::INet,
+            TABLE.into(),
+            base.name().into(),
+            Some(base.chain_type()),
+            Some(base.hook()),
+            Some(base_settings.priority),
+            None,
+            Some(base_settings.policy),
+        ))));
+        for CompiledRule {
+            parent,
+            name,
+            statements,
+            ..
+        } in base_rules
+        {
+            chain_objects.push(add(NfListObject::Chain(Chain::new(
+                NfFamily::INet,
+                TABLE.into(),
+                name.clone(),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ))));
+            if statements.is_empty() {
+                continue;
+            }
+            rule_objects.push(add_rule(name.clone(), statements));
+            rule_objects.push(add_rule(
+                parent.unwrap_or_else(|| base.name().into()),
+                vec![Statement::Jump(JumpTarget { target: name }).into()],
+            ));
+        }
+    }
+    [chain_objects, rule_objects].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn rule(base: BaseChain, name: &str) -> CompiledRule {
+        CompiledRule {
+            base,
+            parent: None,
+            name: name.into(),
+            statements: vec![Statement::Accept(None).into()],
+        }
+    }
+
+    #[test]
+    fn test_base_chain_per_hook() {
+        let rules = [
+            rule(BaseChain::FilterInput, "input_a"),
+            rule(BaseChain::NatPrerouting, "nat_prerouting_b"),
+            rule(BaseChain::FilterInput, "input_c"),
+            // the outgoing segment of a second path through Localhost
+            rule(BaseChain::FilterInput, "input_a"),
+        ];
+        let mut settings = ChainSettings::default();
+        settings.chains.insert(
+            BaseChain::FilterForward,
+            BaseChainSettings {
+                priority: 10,
+                policy: NfChainPolicy::Drop,
+            },
+        );
+        let objects = serde_json::to_value(layout(rules, &settings)).unwrap();
+        let objects = objects.as_array().unwrap();
+        let base_chains: Vec<&Value> = objects
+            .iter()
+            .filter_map(|object| {
+                object["add"]["chain"]
+                    .get("hook")
+                    .map(|_| &object["add"]["chain"])
+            })
+            .collect();
+        // filter chains always exist so their policies apply, NAT and mark
+        // chains only when a path uses them
+        assert_eq!(
+            base_chains,
+            [
+                &json!({"family": "inet", "table": "netgraph", "name": "nat_prerouting",
+                    "type": "nat", "hook": "prerouting", "prio": -100, "policy": "accept"}),
+                &json!({"family": "inet", "table": "netgraph", "name": "input",
+                    "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}),
+                &json!({"family": "inet", "table": "netgraph", "name": "forward",
+                    "type": "filter", "hook": "forward", "prio": 10, "policy": "drop"}),
+                &json!({"family": "inet", "table": "netgraph", "name": "output",
+                    "type": "filter", "hook": "output", "prio": 0, "policy": "accept"}),
+            ]
+        );
+        let jumps: Vec<(&Value, &Value)> = objects
+            .iter()
+            .filter_map(|object| {
+                let rule = object["add"].get("rule")?;
+                Some((&rule["chain"], rule["expr"][0].get("jump")?))
+            })
+            .collect();
+        assert_eq!(
+            jumps,
+            [
+                (
+                    &json!("nat_prerouting"),
+                    &json!({"target": "nat_prerouting_b"})
+                ),
+                (&json!("input"), &json!({"target": "input_a"})),
+                (&json!("input"), &json!({"target": "input_c"})),
+            ]
+        );
+    }
+}
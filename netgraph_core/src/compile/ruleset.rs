@@ -0,0 +1,137 @@
+//! The exported ruleset. Most of it is made of nftables-rs objects and
+//! statements, the rest is written here the way libnftables reads it, for
+//! the expressions and arguments nftables-rs does not know about.
+
+use std::collections::HashSet;
+
+use nftables::expr::Expression;
+use nftables::schema::NfObject;
+use nftables::stmt::{Limit, NATFamily, NATFlag, Operator, Statement};
+use nftables::types::NfFamily;
+use serde::Serialize;
+
+/// The ruleset as written to `nft.json`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Ruleset {
+    #[serde(rename = "nftables")]
+    pub objects: Vec<RulesetObject>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum RulesetObject {
+    Nft(NfObject),
+    Add { add: Object },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Object {
+    Rule(Rule),
+    Limit(LimitObject),
+    Quota(QuotaObject),
+}
+
+/// A rule, with statements nftables-rs may lack.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Rule {
+    pub family: NfFamily,
+    pub table: String,
+    pub chain: String,
+    pub expr: Vec<RuleStatement>,
+}
+
+/// A named limit, with the arguments of the `limit` statement.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitObject {
+    pub family: NfFamily,
+    pub table: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub limit: Limit,
+}
+
+/// A named quota. Unlike the quota object of nftables-rs, it holds more
+/// than 4 GiB.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaObject {
+    pub family: NfFamily,
+    pub table: String,
+    pub name: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum RuleStatement {
+    Nft(Statement),
+    Ext(ExtStatement),
+}
+
+/// Statements nftables-rs cannot express.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtStatement {
+    Match(ExtMatch),
+    /// Reference to a named limit.
+    Limit(String),
+    Snat(Option<Nat>),
+    Dnat(Option<Nat>),
+    Masquerade(Option<Nat>),
+    Redirect(Option<Nat>),
+}
+
+/// A match on an expression nftables-rs cannot express.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExtMatch {
+    pub left: ExtExpression,
+    pub right: Expression,
+    pub op: Operator,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtExpression {
+    /// `meta` keys nftables-rs lacks, like `hour`.
+    Meta {
+        key: String,
+    },
+    /// The cgroup v2 ancestor of the socket at `level`, for the
+    /// `cgroupv2` key.
+    Socket {
+        key: String,
+        level: u32,
+    },
+    Concat(Vec<ExtExpression>),
+}
+
+/// The arguments of a NAT statement, whose port may be a range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Nat {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub addr: Option<Expression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub family: Option<NATFamily>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<Expression>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flags: Option<HashSet<NATFlag>>,
+}
+
+impl From<Statement> for RuleStatement {
+    fn from(statement: Statement) -> Self {
+        RuleStatement::Nft(statement)
+    }
+}
+
+impl From<ExtStatement> for RuleStatement {
+    fn from(statement: ExtStatement) -> Self {
+        RuleStatement::Ext(statement)
+    }
+}
+
+impl From<NfObject> for RulesetObject {
+    fn from(object: NfObject) -> Self {
+        RulesetObject::Nft(object)
+    }
+}
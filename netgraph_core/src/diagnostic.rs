@@ -0,0 +1,26 @@
+use std::fmt::Display;
+
+use egui_node_graph::NodeId;
+
+/// A problem found while compiling the graph. Paths affected by it are left
+/// out of the exported configuration.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Labels of the nodes from Source to the terminal node of the failed
+    /// path, or to the node at fault when it failed before the path was
+    /// complete.
+    pub path: Vec<String>,
+    /// The node at fault, if it is still in the graph.
+    pub node: Option<NodeId>,
+    pub node_label: String,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" → "))?;
+        }
+        write!(f, "{}: {}", self.node_label, self.message)
+    }
+}
@@ -0,0 +1,696 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+
+use map_macro::btree_map;
+use nftables::schema::{NfCmd, NfListObject, NfObject};
+use serde::{Deserialize, Serialize};
+
+use egui_node_graph::{NodeId, OutputId};
+
+use crate::bundle::Route;
+use crate::compile::{self, ChainSettings, Ruleset, RulesetObject};
+use crate::diagnostic::Diagnostic;
+use crate::ip_list::IpList;
+use crate::nf_graph::{params, NFNodeData};
+use crate::{node_key, topology, NFGraph, NodeOutputDB, NodeOutputs, Predicate, PredicateSet};
+
+/// Computes the predicate paths of a graph and compiles them.
+pub struct Evaluator<'a> {
+    pub graph: &'a NFGraph,
+    pub chains: &'a ChainSettings,
+    /// Directory the graph is saved in, where the scripts of custom nodes
+    /// and relative IP list paths are looked up.
+    pub graph_dir: Option<&'a Path>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(graph: &'a NFGraph, chains: &'a ChainSettings, graph_dir: Option<&'a Path>) -> Self {
+        Self {
+            graph,
+            chains,
+            graph_dir,
+        }
+    }
+
+    /// Nodes whose parameters are invalid, with the reason.
+    pub fn invalid_nodes(&self) -> Vec<(NodeId, String)> {
+        self.graph
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| {
+                let err = node.user_data.validate().err()?;
+                Some((node_id, err.to_string()))
+            })
+            .collect()
+    }
+
+    fn apply_custom_node(
+        &self,
+        input: &PredicateSet,
+        node_data: &NFNodeData,
+        output_name: &str,
+    ) -> anyhow::Result<(PredicateSet, HashMap<String, String>)> {
+        #[derive(Serialize, Deserialize)]
+        struct OutputData {
+            predicate_set: PredicateSet,
+            custom_data: HashMap<String, String>,
+        }
+        let path = self
+            .graph_dir
+            .ok_or(anyhow::anyhow!("Save the graph to run custom nodes"))?;
+        let NFNodeData::Custom { plugin, id, .. } = node_data else {
+            return Err(anyhow::anyhow!("Node is not custom"));
+        };
+        let script_path = path.join("plugins").join(plugin).join(id);
+        let mut child = std::process::Command::new(script_path)
+            .arg(output_name)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Cannot execute node script: {}", e))?;
+        // stdin is closed once written, so the script sees the end of input
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(serde_json::to_string(input)?.as_bytes())?;
+        let mut output = vec![];
+        child.stdout.as_mut().unwrap().read_to_end(&mut output)?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("Node script failed with {status}"));
+        }
+        let output = String::from_utf8(output).map_err(|e| anyhow::anyhow!("{}", e))?;
+        let output_data: OutputData = serde_json::from_str(&output)
+            .map_err(|e| anyhow::anyhow!("Cannot deserialize node script output: {}", e))?;
+
+        Ok((output_data.predicate_set, output_data.custom_data))
+    }
+
+    /// Extends `input` with the predicate of a node for packets leaving
+    /// through `output_name`.
+    pub fn apply_node(
+        &self,
+        input: &PredicateSet,
+        node_id: NodeId,
+        node_data: &NFNodeData,
+        output_name: &str,
+    ) -> anyhow::Result<PredicateSet> {
+        let id = node_data.get_id().clone();
+        let predicate = match node_data {
+            NFNodeData::Custom { .. } => {
+                let mut output = self.apply_custom_node(input, node_data, output_name)?.0;
+                let node = node_key(node_id);
+                for predicate in output.iter_mut().filter(|p| p.node.is_empty()) {
+                    predicate.node.clone_from(&node);
+                }
+                return Ok(output);
+            }
+
+            NFNodeData::FileIpList(path) => {
+                let Some(path) = path else {
+                    return Err(anyhow::anyhow!("IP List file is required"));
+                };
+                Predicate::new(
+                    id,
+                    node_id,
+                    btree_map! {
+                        String::from("path") => path.to_string_lossy().to_string(),
+                        String::from("rule") => output_name.to_string(),
+                    },
+                )
+            }
+
+            NFNodeData::SourceAddressFilter(filter)
+            | NFNodeData::DestinationAddressFilter(filter)
+            | NFNodeData::SourcePortFilter(filter)
+            | NFNodeData::DestinationPortFilter(filter)
+            | NFNodeData::InterfaceFilter(filter)
+            | NFNodeData::SocketUserFilter(filter)
+            | NFNodeData::SocketGroupFilter(filter)
+            | NFNodeData::CgroupFilter(filter)
+            | NFNodeData::ProtocolFilter(filter) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("filter") => filter.to_string(),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+
+            NFNodeData::TcpFlagsFilter(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("flags") => options.flags.join(","),
+                    String::from("mask") => options.mask.join(","),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+
+            NFNodeData::TimeFilter(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("hour_from") => options.hour_from.clone(),
+                    String::from("hour_until") => options.hour_until.clone(),
+                    String::from("days") => options.days.join(","),
+                    String::from("date_from") => options.date_from.clone(),
+                    String::from("date_until") => options.date_until.clone(),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+
+            NFNodeData::IcmpTypeFilter(types) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("filter") => types.join(","),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+
+            NFNodeData::CtStateFilter(states) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("filter") => states.join(","),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+
+            NFNodeData::SourceNAT(target)
+            | NFNodeData::DestinationNAT(target)
+            | NFNodeData::Masquerade(target)
+            | NFNodeData::Redirect(target) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("addr") => target.addr.trim().to_string(),
+                    String::from("port") => target.port.trim().to_string(),
+                    String::from("flags") => target.flags().to_string(),
+                },
+            ),
+            NFNodeData::MarkFilter(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("filter") => options.mark.trim().to_string(),
+                    String::from("key") => options.key().to_string(),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+            NFNodeData::SetMark(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("mark") => options.mark.trim().to_string(),
+                    String::from("key") => options.key().to_string(),
+                },
+            ),
+            NFNodeData::RouteVia(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("mark") => options.mark.trim().to_string(),
+                    String::from("table") => options.table.trim().to_string(),
+                    String::from("gateway") => options.gateway.trim().to_string(),
+                    String::from("device") => options.device.trim().to_string(),
+                },
+            ),
+            NFNodeData::LoadBalanceNAT(target) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("backends") => target.backend_list(),
+                    String::from("distribution") => target.distribution().to_string(),
+                    String::from("port") => target.port.trim().to_string(),
+                },
+            ),
+            NFNodeData::RateLimit(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("rate") => options.rate.trim().to_string(),
+                    String::from("burst") => options.burst.trim().to_string(),
+                    String::from("per_source") => options.per_source.to_string(),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+            NFNodeData::Quota(quota) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("quota") => quota.trim().to_string(),
+                    String::from("rule") => output_name.to_string(),
+                },
+            ),
+            NFNodeData::Log(options) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("prefix") => options.prefix.clone(),
+                    String::from("level") => options.level.clone(),
+                    String::from("group") => options.group.trim().to_string(),
+                    String::from("rate") => options.rate.trim().to_string(),
+                },
+            ),
+            NFNodeData::Counter(name) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("name") => name.trim().to_string(),
+                },
+            ),
+            NFNodeData::Reject(reason) => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("reason") => reason.clone(),
+                },
+            ),
+            NFNodeData::Localhost | NFNodeData::Accept | NFNodeData::Drop => {
+                Predicate::new(id, node_id, btree_map! {})
+            }
+            NFNodeData::Router => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("route") => output_name.to_string(),
+                },
+            ),
+            NFNodeData::FamilySplitter => Predicate::new(
+                id,
+                node_id,
+                btree_map! {
+                    String::from("family") => output_name.to_string(),
+                },
+            ),
+            NFNodeData::Source => return Ok(vec![Predicate::new(id, node_id, btree_map! {})]),
+        };
+        Ok([input.clone(), vec![predicate]].concat())
+    }
+
+    /// Applies a node to every path reaching it, once per output. Nodes
+    /// without outputs have a single `terminal` output.
+    pub fn node_outputs(
+        &self,
+        node_inputs: &Vec<PredicateSet>,
+        node_id: NodeId,
+    ) -> anyhow::Result<NodeOutputs, anyhow::Error> {
+        let mut output_predicates = BTreeMap::new();
+        let node = self
+            .graph
+            .nodes
+            .get(node_id)
+            .ok_or(anyhow::anyhow!("Node not found"))?;
+        let node_data = &node.user_data;
+        let mut outputs = node.outputs.clone();
+
+        if outputs.is_empty() {
+            outputs.push(("terminal".into(), OutputId::default()));
+        }
+        for (output_name, _) in outputs {
+            let mut node_output = Vec::new();
+            for input in node_inputs {
+                node_output.push(self.apply_node(input, node_id, node_data, &output_name)?);
+            }
+            output_predicates.insert(output_name.clone(), node_output);
+        }
+
+        Ok(output_predicates)
+    }
+
+    /// Computes the outputs of a node and of every node it depends on. A
+    /// node failing for a path reaching it is reported in `diagnostics`
+    /// with that path, which is left out instead of failing the whole graph.
+    pub fn recurse_node_outputs(
+        &self,
+        this_node_id: NodeId,
+        node_output_db: &mut NodeOutputDB,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> anyhow::Result<()> {
+        if node_output_db.contains_key(&this_node_id) {
+            return Ok(());
+        }
+        let this_node = self
+            .graph
+            .nodes
+            .get(this_node_id)
+            .ok_or(anyhow::Error::msg("Node not found"))?;
+        let this_node_inputs = if let Some((_, this_node_input_id)) = this_node.inputs.first() {
+            let mut this_node_inputs = vec![];
+            let mut dep_node_ids = topology::connected_sender_nodes(self.graph, this_node_id);
+            // a node connected through several outputs is listed once per
+            // connection, but all of its outputs are collected below
+            dep_node_ids.sort();
+            dep_node_ids.dedup();
+            for dep_node_id in dep_node_ids {
+                self.recurse_node_outputs(dep_node_id, node_output_db, diagnostics)?;
+                let dep_node = &self.graph.nodes[dep_node_id];
+                for (output_name, output_id) in &dep_node.outputs {
+                    if self.graph.connections.get(*output_id) != Some(this_node_input_id) {
+                        continue;
+                    }
+                    if let Some(output) = node_output_db[&dep_node_id].get(output_name) {
+                        this_node_inputs.extend(output.iter().cloned());
+                    }
+                }
+            }
+            this_node_inputs
+        } else {
+            vec![PredicateSet::new()]
+        };
+
+        let mut outputs = NodeOutputs::new();
+        for input in this_node_inputs {
+            match self.node_outputs(&vec![input.clone()], this_node_id) {
+                Ok(input_outputs) => {
+                    for (output_name, paths) in input_outputs {
+                        outputs.entry(output_name).or_default().extend(paths);
+                    }
+                }
+                Err(err) => {
+                    let mut path = self.path_labels(&input);
+                    path.push(this_node.label.clone());
+                    diagnostics.push(Diagnostic {
+                        path,
+                        node: Some(this_node_id),
+                        node_label: this_node.label.clone(),
+                        message: err.to_string(),
+                    });
+                }
+            }
+        }
+        node_output_db.insert(this_node_id, outputs);
+        Ok(())
+    }
+
+    /// Finds the node a predicate was produced by, and its label.
+    pub fn node_label(&self, key: &str) -> (Option<NodeId>, String) {
+        self.graph
+            .nodes
+            .iter()
+            .find(|(node_id, _)| node_key(*node_id) == key)
+            .map_or((None, key.to_string()), |(node_id, node)| {
+                (Some(node_id), node.label.clone())
+            })
+    }
+
+    /// Labels of the nodes `path` passes through, once per node.
+    fn path_labels(&self, path: &PredicateSet) -> Vec<String> {
+        let mut nodes: Vec<_> = path.iter().map(|p| p.node.as_str()).collect();
+        nodes.dedup();
+        nodes
+            .into_iter()
+            .map(|node| self.node_label(node).1)
+            .collect()
+    }
+
+    /// Every path from Source to a terminal node, ordered by the nodes they
+    /// pass through, and the nodes that failed along the way.
+    pub fn paths(&self) -> anyhow::Result<(Vec<PredicateSet>, Vec<Diagnostic>)> {
+        let mut diagnostics = vec![];
+        let mut node_output_db = NodeOutputDB::new();
+        for node_id in self.graph.iter_nodes() {
+            self.recurse_node_outputs(node_id, &mut node_output_db, &mut diagnostics)?;
+        }
+        let mut paths: Vec<PredicateSet> = self
+            .graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.outputs.is_empty())
+            .filter_map(|(node_id, _)| Some(node_output_db.get(&node_id)?.get("terminal")?.clone()))
+            .flatten()
+            .collect();
+        // the order of nodes in the graph depends on editing history, so
+        // paths are ordered by the nodes they pass through instead
+        paths.sort_by_cached_key(|path| serde_json::to_string(path).unwrap_or_default());
+        Ok((paths, diagnostics))
+    }
+
+    /// Compiles the graph into an nftables ruleset and the policy routing
+    /// to export next to it, evaluating the graph once. Paths that cannot be
+    /// compiled are left out and reported in the returned diagnostics.
+    pub fn compile(&self) -> anyhow::Result<(Ruleset, Vec<Route>, Vec<Diagnostic>)> {
+        let (paths, mut diagnostics) = self.paths()?;
+        let ip_lists = load_ip_lists(&paths, self.graph_dir)?;
+        let mut rules = vec![];
+        for path in &paths {
+            match compile::evaluate_path(path, &ip_lists) {
+                Ok(path_rules) => rules.extend(path_rules),
+                Err(err) => {
+                    let (node, node_label) = self.node_label(&path[err.predicate].node);
+                    diagnostics.push(Diagnostic {
+                        path: self.path_labels(path),
+                        node,
+                        node_label,
+                        message: err.error.to_string(),
+                    });
+                }
+            }
+        }
+        let nf_objects = compile::layout(rules, self.chains);
+        let table = NfListObject::Table(nftables::schema::Table::new(
+            nftables::types::NfFamily::INet,
+            compile::TABLE.into(),
+        ));
+        // adding the table first lets the delete succeed on a clean system,
+        // so the whole file replaces the previous table in one transaction
+        let table = vec![
+            NfObject::CmdObject(NfCmd::Add(table.clone())),
+            NfObject::CmdObject(NfCmd::Delete(table.clone())),
+            NfObject::CmdObject(NfCmd::Add(table)),
+        ];
+        let sets = ip_lists
+            .values()
+            .map(|ip_list| NfObject::CmdObject(NfCmd::Add(NfListObject::Set(ip_list.to_set()))))
+            .collect();
+        let counters = counter_names(&paths)
+            .into_iter()
+            .map(|name| {
+                NfObject::CmdObject(NfCmd::Add(NfListObject::Counter(
+                    nftables::schema::Counter {
+                        family: "inet".into(),
+                        table: compile::TABLE.into(),
+                        name,
+                        handle: None,
+                        packets: Some(0),
+                        bytes: Some(0),
+                    },
+                )))
+            })
+            .collect();
+        let objects: Vec<NfObject> = [table, sets, counters].concat();
+        let nft = Ruleset {
+            objects: objects
+                .into_iter()
+                .map(RulesetObject::from)
+                .chain(compile::limit_objects(&paths))
+                .chain(nf_objects)
+                .collect(),
+        };
+        Ok((nft, routes(&paths)?, diagnostics))
+    }
+}
+
+fn load_ip_lists(
+    paths: &[PredicateSet],
+    graph_dir: Option<&Path>,
+) -> anyhow::Result<BTreeMap<String, IpList>> {
+    let mut ip_lists = BTreeMap::new();
+    let list_paths = paths
+        .iter()
+        .flatten()
+        .filter(|predicate| predicate.variant == "core:file_ip_list")
+        .filter_map(|predicate| predicate.params.get("path"));
+    for list_path in list_paths {
+        if !ip_lists.contains_key(list_path) {
+            let ip_list = IpList::read(list_path.as_ref(), graph_dir)?;
+            ip_lists.insert(list_path.clone(), ip_list);
+        }
+    }
+    Ok(ip_lists)
+}
+
+/// The policy routing of the Route Via nodes on the paths. Nodes sharing
+/// a mark or a table must route the same way.
+fn routes(paths: &[PredicateSet]) -> anyhow::Result<Vec<Route>> {
+    let mut routes: Vec<Route> = vec![];
+    let route_predicates = paths
+        .iter()
+        .flatten()
+        .filter(|predicate| predicate.variant == "core:route_via");
+    for predicate in route_predicates {
+        let param = |name: &str| predicate.params.get(name).map_or("", String::as_str);
+        let route = Route {
+            mark: params::mark(param("mark"))?,
+            table: params::route_table(param("table"))?,
+            gateway: match param("gateway") {
+                "" => None,
+                gateway => Some(gateway.parse()?),
+            },
+            device: Some(param("device").to_string()).filter(|device| !device.is_empty()),
+        };
+        let conflict = routes.iter().find(|known| {
+            (known.mark == route.mark || known.table == route.table) && **known != route
+        });
+        if let Some(known) = conflict {
+            return Err(anyhow::anyhow!(
+                "Route Via nodes with mark {:#x} and {:#x} route differently through table {} and {}",
+                known.mark,
+                route.mark,
+                known.table,
+                route.table
+            ));
+        }
+        if !routes.contains(&route) {
+            routes.push(route);
+        }
+    }
+    routes.sort();
+    Ok(routes)
+}
+
+/// Names of the counter objects the Counter nodes on the paths count in.
+fn counter_names(paths: &[PredicateSet]) -> BTreeSet<String> {
+    paths
+        .iter()
+        .flatten()
+        .filter(|predicate| predicate.variant == "core:counter")
+        .filter_map(|predicate| predicate.params.get("name"))
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeTemplateTrait;
+    use nftables::schema::Chain;
+
+    use super::*;
+    use crate::import;
+    use crate::nf_graph::NFGraphState;
+
+    const RULESET: &str = "*filter
+:INPUT DROP [0:0]
+-A INPUT -p tcp --dport 22 -j ACCEPT
+-A INPUT -s 10.0.0.0/8 -d fd00::1 -j ACCEPT
+COMMIT
+";
+
+    fn imported_graph() -> (NFGraph, NFGraphState) {
+        let mut graph = NFGraph::new();
+        let mut user_state = NFGraphState::default();
+        let mut nodes = vec![];
+        for node in [NFNodeData::Source, NFNodeData::Localhost] {
+            let label = node.node_graph_label(&mut user_state);
+            nodes.push(graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            }));
+        }
+        let ruleset = import::parse_ruleset(RULESET).unwrap();
+        import::build_graph(&ruleset, &mut graph, &mut user_state, nodes[0], nodes[1]);
+        (graph, user_state)
+    }
+
+    #[test]
+    fn test_compile_graph() {
+        let (graph, user_state) = imported_graph();
+        let evaluator = Evaluator::new(&graph, &user_state.chains, None);
+        assert!(evaluator.invalid_nodes().is_empty());
+
+        let (paths, diagnostics) = evaluator.paths().unwrap();
+        assert!(diagnostics.is_empty());
+        let variants: Vec<Vec<_>> = paths
+            .iter()
+            .map(|path| path.iter().map(|p| p.variant.as_str()).collect())
+            .collect();
+        assert!(variants.contains(&vec![
+            "core:source",
+            "core:family_splitter",
+            "core:protocol_filter",
+            "core:destination_port_filter",
+            "core:localhost",
+            "core:accept",
+        ]));
+
+        let (nft, _, diagnostics) = evaluator.compile().unwrap();
+        // both packets failing the port filter and packets passing it reach
+        // the address filters
+        assert_eq!(diagnostics.len(), 2);
+        for diagnostic in &diagnostics {
+            assert_eq!(diagnostic.node_label, "Destination Address Filter");
+            assert_eq!(diagnostic.path.last().unwrap(), "Accept");
+        }
+
+        let chains: Vec<_> = nft
+            .objects
+            .iter()
+            .filter_map(|object| match object {
+                RulesetObject::Nft(NfObject::CmdObject(NfCmd::Add(NfListObject::Chain(
+                    Chain { name, .. },
+                )))) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(chains.contains(&"input"));
+        assert_eq!(
+            chains
+                .iter()
+                .filter(|name| name.starts_with("input_"))
+                .count(),
+            // the ssh rule, and IPv6 packets bypassing the IPv4 table
+            2
+        );
+    }
+
+    #[test]
+    fn test_failing_node_reports_path() {
+        let mut graph = NFGraph::new();
+        let mut user_state = NFGraphState::default();
+        let mut nodes = vec![];
+        for node in [
+            NFNodeData::Source,
+            NFNodeData::FamilySplitter,
+            NFNodeData::FileIpList(None),
+            NFNodeData::Accept,
+        ] {
+            let label = node.node_graph_label(&mut user_state);
+            nodes.push(graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            }));
+        }
+        let connect = |graph: &mut NFGraph, from: usize, output: &str, to: usize| {
+            let output = graph[nodes[from]].get_output(output).unwrap();
+            let input = graph[nodes[to]].input_ids().next().unwrap();
+            graph.add_connection(output, input);
+        };
+        connect(&mut graph, 0, "incoming", 1);
+        connect(&mut graph, 1, "ipv4", 2);
+        connect(&mut graph, 1, "ipv6", 3);
+        connect(&mut graph, 2, "match", 3);
+
+        // only the path through the list is lost
+        let evaluator = Evaluator::new(&graph, &user_state.chains, None);
+        let (paths, diagnostics) = evaluator.paths().unwrap();
+        assert_eq!(paths.len(), 1);
+        let [diagnostic] = diagnostics.as_slice() else {
+            panic!("expected one diagnostic, got {diagnostics:?}");
+        };
+        assert_eq!(diagnostic.node, Some(nodes[2]));
+        assert_eq!(diagnostic.message, "IP List file is required");
+        assert_eq!(
+            diagnostic.path,
+            [
+                "Incoming Source",
+                "Family Splitter",
+                diagnostic.node_label.as_str()
+            ]
+        );
+    }
+}
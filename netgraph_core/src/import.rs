@@ -0,0 +1,375 @@
+use std::collections::{BTreeMap, HashMap};
+
+use nftables::types::NfChainPolicy;
+
+use egui_node_graph::{Graph, NodeId, NodeTemplateTrait, OutputId};
+
+use crate::compile::BaseChain;
+use crate::nf_graph::{DataType, NFFamily, NFGraphState, NFNodeData, ValueType};
+
+mod iptables;
+mod nft;
+
+pub use iptables::parse_iptables_save;
+pub use nft::parse_nft_json;
+
+/// A node a packet has to pass through to stay in a rule. Packets leaving
+/// through the `fail` output continue with the next rule.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub node: NFNodeData,
+    pub pass: &'static str,
+    pub fail: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    Accept,
+    Drop,
+    /// A Reject node with the given reason.
+    Reject(String),
+    /// A NAT node, after which the packet is accepted by the chain.
+    Nat(NFNodeData),
+    Jump(String),
+    Return,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedRule {
+    pub conditions: Vec<Condition>,
+    pub action: Action,
+    /// Where the rule was read from, e.g. `filter INPUT, rule 3`.
+    pub location: String,
+    /// Options the Interface Filters of the rule were read from.
+    pub interfaces: Vec<InterfaceOption>,
+}
+
+/// An option matching the incoming or the outgoing interface. An Interface
+/// Filter matches the incoming one of incoming packets and the outgoing one
+/// of forwarded and outgoing packets, so the rule is only imported on the
+/// matching side.
+#[derive(Debug, Clone)]
+pub struct InterfaceOption {
+    /// The option as written, e.g. `iifname`.
+    pub name: String,
+    pub incoming: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportedChain {
+    pub rules: Vec<ImportedRule>,
+    pub priority: Option<i32>,
+    pub policy: Option<NfChainPolicy>,
+    /// Family of the table the chain is in, packets of the other family
+    /// pass the chain untouched.
+    pub family: NFFamily,
+}
+
+/// A ruleset of another tool, reduced to what the core nodes can express.
+#[derive(Debug, Clone, Default)]
+pub struct Ruleset {
+    pub base_chains: BTreeMap<BaseChain, ImportedChain>,
+    pub chains: BTreeMap<String, ImportedChain>,
+    /// Everything that could not be imported.
+    pub unsupported: Vec<String>,
+}
+
+/// Reads a ruleset exported by `nft -j list ruleset`, `iptables-save` or
+/// `ip6tables-save`.
+pub fn parse_ruleset(text: &str) -> anyhow::Result<Ruleset> {
+    if text.trim_start().starts_with('{') {
+        parse_nft_json(text)
+    } else {
+        parse_iptables_save(text)
+    }
+}
+
+/// Packets leaving a sequence of rules, by whether a rule accepted them or
+/// none of the rules applied.
+struct Exits {
+    accepted: Vec<OutputId>,
+    fallthrough: Vec<OutputId>,
+}
+
+impl Condition {
+    pub fn new(node: NFNodeData, negated: bool) -> Self {
+        let (pass, fail) = if negated {
+            ("non-match", "match")
+        } else {
+            ("match", "non-match")
+        };
+        Self { node, pass, fail }
+    }
+
+    /// Makes sure the node accepts the imported parameters.
+    pub fn checked(node: NFNodeData, negated: bool) -> anyhow::Result<Self> {
+        node.validate()?;
+        Ok(Self::new(node, negated))
+    }
+}
+
+impl ImportedChain {
+    fn accepts_by_default(&self) -> bool {
+        self.policy != Some(NfChainPolicy::Drop)
+    }
+}
+
+struct GraphBuilder<'a> {
+    graph: &'a mut Graph<NFNodeData, DataType, ValueType>,
+    user_state: &'a mut NFGraphState,
+    ruleset: &'a Ruleset,
+    drop: Option<NodeId>,
+    /// Whether the chains being wired see incoming packets, rather than
+    /// forwarded or outgoing ones.
+    incoming: bool,
+    unsupported: Vec<String>,
+}
+
+/// Wires the rules of `ruleset` into `graph`, starting from the `source`
+/// and `localhost` nodes. Chain settings of the base chains are copied into
+/// `user_state`. Returns what could not be represented.
+pub fn build_graph(
+    ruleset: &Ruleset,
+    graph: &mut Graph<NFNodeData, DataType, ValueType>,
+    user_state: &mut NFGraphState,
+    source: NodeId,
+    localhost: NodeId,
+) -> Vec<String> {
+    for (base, chain) in &ruleset.base_chains {
+        let mut settings = user_state.chains.get(*base);
+        settings.priority = chain.priority.unwrap_or(settings.priority);
+        settings.policy = chain.policy.clone().unwrap_or(NfChainPolicy::Accept);
+        user_state.chains.chains.insert(*base, settings);
+    }
+
+    let mut builder = GraphBuilder {
+        graph,
+        user_state,
+        ruleset,
+        drop: None,
+        incoming: true,
+        unsupported: ruleset.unsupported.clone(),
+    };
+    let empty = ImportedChain::default();
+    let chain = |base| ruleset.base_chains.get(&base).unwrap_or(&empty);
+
+    let source_output = builder.output(source, "incoming");
+    let prerouting = builder.chain(vec![source_output], chain(BaseChain::NatPrerouting));
+    let prerouted = [prerouting.accepted, prerouting.fallthrough].concat();
+    // the Router splits the packets before the input chain, so the forward
+    // chain sees every forwarded packet, whatever the input chain does
+    let (input_entries, forwarded) = if ruleset.base_chains.contains_key(&BaseChain::FilterForward)
+    {
+        let router = builder.add_node(NFNodeData::Router);
+        builder.connect(prerouted, router);
+        (
+            vec![builder.output(router, "local")],
+            Some(builder.output(router, "forwarded")),
+        )
+    } else {
+        (prerouted, None)
+    };
+    let input = builder.chain(input_entries, chain(BaseChain::FilterInput));
+    builder.connect(input.accepted, localhost);
+    if chain(BaseChain::FilterInput).accepts_by_default() {
+        builder.connect(input.fallthrough, localhost);
+    }
+    if let Some(forwarded) = forwarded {
+        builder.drop = None;
+        builder.incoming = false;
+        builder.accepted_chain(vec![forwarded], chain(BaseChain::FilterForward));
+    }
+
+    // paths only compile once they reach a verdict, so Local Machine is
+    // connected even without output rules
+    let localhost_output = builder.output(localhost, "outgoing");
+    builder.drop = None;
+    builder.incoming = false;
+    builder.accepted_chain(vec![localhost_output], chain(BaseChain::FilterOutput));
+    builder.unsupported
+}
+
+impl<'a> GraphBuilder<'a> {
+    /// Runs packets through a filter chain and the postrouting chain, ending
+    /// at an Accept node.
+    fn accepted_chain(&mut self, entries: Vec<OutputId>, chain: &'a ImportedChain) {
+        let exits = self.chain(entries, chain);
+        let mut accepted = exits.accepted;
+        if chain.accepts_by_default() {
+            accepted.extend(exits.fallthrough);
+        }
+        let exits = match self.ruleset.base_chains.get(&BaseChain::NatPostrouting) {
+            Some(postrouting) => self.chain(accepted, postrouting),
+            None => Exits {
+                accepted,
+                fallthrough: vec![],
+            },
+        };
+        let accepted = [exits.accepted, exits.fallthrough].concat();
+        if !accepted.is_empty() {
+            let accept = self.add_node(NFNodeData::Accept);
+            self.connect(accepted, accept);
+        }
+    }
+
+    fn chain(&mut self, entries: Vec<OutputId>, chain: &'a ImportedChain) -> Exits {
+        let (entries, bypass) = match chain.family {
+            _ if entries.is_empty() => (entries, vec![]),
+            NFFamily::Inet => (entries, vec![]),
+            family => {
+                let splitter = self.add_node(NFNodeData::FamilySplitter);
+                self.connect(entries, splitter);
+                let (pass, bypass) = if family == NFFamily::IPv4 {
+                    ("ipv4", "ipv6")
+                } else {
+                    ("ipv6", "ipv4")
+                };
+                (
+                    vec![self.output(splitter, pass)],
+                    vec![self.output(splitter, bypass)],
+                )
+            }
+        };
+        let mut exits = self.rules(entries, &chain.rules, 0);
+        exits.accepted.extend(bypass);
+        exits
+    }
+
+    fn rules(
+        &mut self,
+        mut entries: Vec<OutputId>,
+        rules: &'a [ImportedRule],
+        depth: usize,
+    ) -> Exits {
+        let mut accepted = vec![];
+        for rule in rules {
+            if entries.is_empty() {
+                break;
+            }
+            if let Some(option) = rule.interfaces.iter().find(|o| o.incoming != self.incoming) {
+                let side = if self.incoming {
+                    "for incoming packets, where Interface Filters match the incoming interface"
+                } else {
+                    "for forwarded and outgoing packets, where Interface Filters match the \
+                     outgoing interface"
+                };
+                let message = format!(
+                    "{}: rule matching `{}` is left out {side}",
+                    rule.location, option.name
+                );
+                if !self.unsupported.contains(&message) {
+                    self.unsupported.push(message);
+                }
+                continue;
+            }
+            let mut fallthrough = vec![];
+            for condition in &rule.conditions {
+                let node = self.add_node(condition.node.clone());
+                self.connect(entries, node);
+                fallthrough.push(self.output(node, condition.fail));
+                entries = vec![self.output(node, condition.pass)];
+            }
+            match &rule.action {
+                Action::Accept => accepted.extend(entries),
+                Action::Drop => {
+                    let drop = match self.drop {
+                        Some(drop) => drop,
+                        None => {
+                            let drop = self.add_node(NFNodeData::Drop);
+                            *self.drop.insert(drop)
+                        }
+                    };
+                    self.connect(entries, drop);
+                }
+                Action::Reject(reason) => {
+                    let reject = self.add_node(NFNodeData::Reject(reason.clone()));
+                    self.connect(entries, reject);
+                }
+                Action::Nat(nat) => {
+                    let node = self.add_node(nat.clone());
+                    self.connect(entries, node);
+                    accepted.push(self.output(node, ""));
+                }
+                Action::Jump(target) => match self.ruleset.chains.get(target) {
+                    Some(chain) if depth < 16 => {
+                        let exits = self.rules(entries, &chain.rules, depth + 1);
+                        accepted.extend(exits.accepted);
+                        fallthrough.extend(exits.fallthrough);
+                    }
+                    Some(_) => {
+                        self.unsupported
+                            .push(format!("Jumps to `{target}` are nested too deep"));
+                        fallthrough.extend(entries);
+                    }
+                    None => {
+                        self.unsupported
+                            .push(format!("Chain `{target}` is jumped to but not defined"));
+                        fallthrough.extend(entries);
+                    }
+                },
+                Action::Return => fallthrough.extend(entries),
+            }
+            entries = fallthrough;
+        }
+        Exits {
+            accepted,
+            fallthrough: entries,
+        }
+    }
+
+    fn add_node(&mut self, node: NFNodeData) -> NodeId {
+        let label = node.node_graph_label(self.user_state);
+        let user_data = node.user_data(self.user_state);
+        self.graph.add_node(label, user_data, |graph, node_id| {
+            node.build_node(graph, self.user_state, node_id);
+        })
+    }
+
+    fn output(&self, node_id: NodeId, name: &str) -> OutputId {
+        self.graph[node_id]
+            .get_output(name)
+            .expect("core nodes have fixed outputs")
+    }
+
+    fn connect(&mut self, outputs: Vec<OutputId>, node_id: NodeId) {
+        let Some(input) = self.graph[node_id].input_ids().next() else {
+            return;
+        };
+        for output in outputs {
+            self.graph.add_connection(output, input);
+        }
+    }
+}
+
+/// Places every node one column right of the rightmost node connected to it,
+/// stacking nodes of the same column in creation order. Positions are
+/// `[x, y]` in editor points.
+pub fn layout(graph: &Graph<NFNodeData, DataType, ValueType>) -> Vec<(NodeId, [f32; 2])> {
+    let mut columns: HashMap<NodeId, usize> = graph.iter_nodes().map(|id| (id, 0)).collect();
+    // the graph has no loops, so the longest path settles within a pass per node
+    for _ in 0..graph.nodes.len() {
+        let mut changed = false;
+        for (input, output) in graph.iter_connections() {
+            let column = columns[&graph[output].node] + 1;
+            let receiver = columns.get_mut(&graph[input].node).unwrap();
+            if *receiver < column {
+                *receiver = column;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut rows: HashMap<usize, usize> = HashMap::new();
+    graph
+        .iter_nodes()
+        .map(|node_id| {
+            let column = columns[&node_id];
+            let row = rows.entry(column).or_default();
+            let pos = [column as f32 * 260.0, *row as f32 * 180.0];
+            *row += 1;
+            (node_id, pos)
+        })
+        .collect()
+}
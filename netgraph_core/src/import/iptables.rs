@@ -0,0 +1,578 @@
+use std::collections::HashMap;
+
+use nftables::types::NfChainPolicy;
+
+use super::{Action, Condition, ImportedChain, ImportedRule, InterfaceOption, Ruleset};
+use crate::compile::BaseChain;
+use crate::nf_graph::{params, NFFamily, NFNodeData, NatTarget};
+
+/// Match modules whose options are understood, or which do not affect
+/// where packets go.
+const MODULES: [&str; 9] = [
+    "tcp",
+    "udp",
+    "udplite",
+    "sctp",
+    "dccp",
+    "multiport",
+    "conntrack",
+    "state",
+    "comment",
+];
+
+/// Targets which do not change where packets go, rules using them are left out.
+const NON_TERMINATING_TARGETS: [&str; 3] = ["LOG", "NFLOG", "ULOG"];
+
+/// Reads the output of `iptables-save` or `ip6tables-save`. Only the
+/// `filter` and `nat` tables are imported.
+pub fn parse_iptables_save(text: &str) -> anyhow::Result<Ruleset> {
+    let family = if text.contains("ip6tables-save") {
+        NFFamily::IPv6
+    } else {
+        NFFamily::IPv4
+    };
+    let mut ruleset = Ruleset::default();
+    let mut table = None;
+    let mut skipped_rules = 0;
+    let mut rule_numbers: HashMap<String, usize> = HashMap::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line == "COMMIT" {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('*') {
+            table = Some(name.to_string());
+            if !["filter", "nat"].contains(&name) {
+                ruleset
+                    .unsupported
+                    .push(format!("Table `{name}` is not supported"));
+            }
+            continue;
+        }
+        let Some(table) = table.as_deref() else {
+            return Err(anyhow::anyhow!(
+                "Line {}: rule outside of a table, is this an iptables-save output?",
+                line_no + 1
+            ));
+        };
+        if !["filter", "nat"].contains(&table) {
+            skipped_rules += usize::from(line.starts_with("-A"));
+            continue;
+        }
+
+        if let Some(declaration) = line.strip_prefix(':') {
+            let mut parts = declaration.split_whitespace();
+            let (Some(name), Some(policy)) = (parts.next(), parts.next()) else {
+                return Err(anyhow::anyhow!("Line {}: malformed chain", line_no + 1));
+            };
+            let chain = ImportedChain {
+                rules: vec![],
+                priority: None,
+                policy: match policy {
+                    "ACCEPT" => Some(NfChainPolicy::Accept),
+                    "DROP" => Some(NfChainPolicy::Drop),
+                    _ => None,
+                },
+                family,
+            };
+            match base_chain(table, name) {
+                Some(base) => {
+                    ruleset.base_chains.insert(base, chain);
+                }
+                None if policy == "-" => {
+                    ruleset.chains.insert(chain_key(table, name), chain);
+                }
+                None => ruleset
+                    .unsupported
+                    .push(format!("Chain `{name}` of the `{table}` table")),
+            }
+            continue;
+        }
+
+        let mut tokens = tokenize(line)?;
+        // `iptables-save -c` prefixes rules with their counters
+        if tokens.first().is_some_and(|token| token.starts_with('[')) {
+            tokens.remove(0);
+        }
+        let (Some("-A"), Some(chain_name)) = (tokens.first().map(String::as_str), tokens.get(1))
+        else {
+            return Err(anyhow::anyhow!("Line {}: expected a rule", line_no + 1));
+        };
+        let rule = import_rule(table, &tokens[2..], &ruleset);
+        let chain = match base_chain(table, chain_name) {
+            Some(base) => ruleset.base_chains.get_mut(&base),
+            None => ruleset.chains.get_mut(&chain_key(table, chain_name)),
+        };
+        let Some(chain) = chain else {
+            skipped_rules += 1;
+            continue;
+        };
+        let rule_number = rule_numbers
+            .entry(chain_key(table, chain_name))
+            .or_default();
+        *rule_number += 1;
+        let location = format!("{table} {chain_name}, rule {rule_number}");
+        if tokens
+            .iter()
+            .any(|token| token == "-g" || token == "--goto")
+        {
+            ruleset
+                .unsupported
+                .push(format!("{location}: `-g` is imported as `-j`"));
+        }
+        match rule {
+            Ok(Some(rule)) => chain.rules.push(ImportedRule { location, ..rule }),
+            Ok(None) => ruleset
+                .unsupported
+                .push(format!("{location}: rule without a verdict is left out")),
+            Err(err) => ruleset.unsupported.push(format!("{location}: {err}")),
+        }
+    }
+    if skipped_rules > 0 {
+        ruleset.unsupported.push(format!(
+            "{skipped_rules} rule(s) of unsupported tables or chains"
+        ));
+    }
+    Ok(ruleset)
+}
+
+fn base_chain(table: &str, chain: &str) -> Option<BaseChain> {
+    match (table, chain) {
+        ("filter", "INPUT") => Some(BaseChain::FilterInput),
+        ("filter", "FORWARD") => Some(BaseChain::FilterForward),
+        ("filter", "OUTPUT") => Some(BaseChain::FilterOutput),
+        ("nat", "PREROUTING") => Some(BaseChain::NatPrerouting),
+        ("nat", "POSTROUTING") => Some(BaseChain::NatPostrouting),
+        _ => None,
+    }
+}
+
+fn chain_key(table: &str, chain: &str) -> String {
+    format!("{table} {chain}")
+}
+
+/// Splits a rule into arguments the way the shell would, as comments may be
+/// quoted.
+fn tokenize(line: &str) -> anyhow::Result<Vec<String>> {
+    let mut tokens = vec![];
+    let mut token: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => loop {
+                let token = token.get_or_insert_with(String::new);
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => token.extend(chars.next()),
+                    Some(c) => token.push(c),
+                    None => return Err(anyhow::anyhow!("Unterminated quote in `{line}`")),
+                }
+            },
+            c if c.is_whitespace() => tokens.extend(token.take()),
+            c => token.get_or_insert_with(String::new).push(c),
+        }
+    }
+    tokens.extend(token);
+    Ok(tokens)
+}
+
+fn import_rule(
+    table: &str,
+    tokens: &[String],
+    ruleset: &Ruleset,
+) -> anyhow::Result<Option<ImportedRule>> {
+    let mut conditions = vec![];
+    let mut interfaces = vec![];
+    let mut target = None;
+    let mut nat = NatTarget::default();
+    let mut reject_with = None;
+    let mut negated = false;
+    let mut tokens = tokens.iter().map(String::as_str);
+    while let Some(option) = tokens.next() {
+        if option == "!" {
+            negated = true;
+            continue;
+        }
+        let mut value = || {
+            let value = tokens
+                .next()
+                .ok_or(anyhow::anyhow!("`{option}` needs a value"))?;
+            // older versions write the negation after the option
+            if value == "!" {
+                negated = true;
+                return tokens
+                    .next()
+                    .ok_or(anyhow::anyhow!("`{option}` needs a value"));
+            }
+            Ok(value)
+        };
+        let node = match option {
+            "-p" | "--protocol" => Some(NFNodeData::ProtocolFilter(value()?.into())),
+            "-s" | "--source" => Some(NFNodeData::SourceAddressFilter(value()?.into())),
+            "-d" | "--destination" => Some(NFNodeData::DestinationAddressFilter(value()?.into())),
+            "-i" | "--in-interface" | "-o" | "--out-interface" => {
+                let interface = value()?.replace('+', "*");
+                interfaces.push(InterfaceOption {
+                    name: option.to_string(),
+                    incoming: matches!(option, "-i" | "--in-interface"),
+                });
+                Some(NFNodeData::InterfaceFilter(interface))
+            }
+            "--sport" | "--source-port" | "--sports" | "--source-ports" => {
+                Some(NFNodeData::SourcePortFilter(value()?.replace(':', "-")))
+            }
+            "--dport" | "--destination-port" | "--dports" | "--destination-ports" => Some(
+                NFNodeData::DestinationPortFilter(value()?.replace(':', "-")),
+            ),
+            "--ctstate" | "--state" => {
+                Some(NFNodeData::CtStateFilter(params::ct_states(value()?)?))
+            }
+            "-m" | "--match" => {
+                let module = value()?;
+                if !MODULES.contains(&module) {
+                    return Err(anyhow::anyhow!("`-m {module}` is not supported"));
+                }
+                None
+            }
+            "--comment" => {
+                value()?;
+                None
+            }
+            "-j" | "--jump" | "-g" | "--goto" => {
+                let value = value()?;
+                if NON_TERMINATING_TARGETS.contains(&value) {
+                    return Ok(None);
+                }
+                target = Some(value.to_string());
+                None
+            }
+            "--reject-with" => {
+                reject_with = Some(reject_reason(value()?)?);
+                None
+            }
+            "--to-destination" | "--to-source" => {
+                let to = value()?;
+                nat = NatTarget {
+                    persistent: nat.persistent,
+                    random: nat.random,
+                    ..nat_target(to)
+                };
+                None
+            }
+            "--to-ports" => {
+                nat.port = value()?.to_string();
+                None
+            }
+            "--persistent" => {
+                nat.persistent = true;
+                None
+            }
+            "--random" => {
+                nat.random = true;
+                None
+            }
+            option => return Err(anyhow::anyhow!("`{option}` is not supported")),
+        };
+        if let Some(node) = node {
+            conditions.push(Condition::checked(node, negated)?);
+        }
+        negated = false;
+    }
+
+    let Some(target) = target else {
+        return Ok(None);
+    };
+    let translate = |node: fn(NatTarget) -> NFNodeData| {
+        if nat.addr.is_empty() {
+            return Err(anyhow::anyhow!("`{target}` needs a target address"));
+        }
+        Ok(Action::Nat(node(nat.clone())))
+    };
+    let action = match target.as_str() {
+        "ACCEPT" => Action::Accept,
+        "DROP" => Action::Drop,
+        "REJECT" => Action::Reject(reject_with.unwrap_or("port-unreachable".into())),
+        "RETURN" => Action::Return,
+        "DNAT" => translate(NFNodeData::DestinationNAT)?,
+        "SNAT" => translate(NFNodeData::SourceNAT)?,
+        "MASQUERADE" => Action::Nat(NFNodeData::Masquerade(nat.clone())),
+        "REDIRECT" => Action::Nat(NFNodeData::Redirect(nat.clone())),
+        chain if ruleset.chains.contains_key(&chain_key(table, chain)) => {
+            Action::Jump(chain_key(table, chain))
+        }
+        target => return Err(anyhow::anyhow!("`-j {target}` is not supported")),
+    };
+    if let Action::Nat(node) = &action {
+        node.validate()?;
+    }
+    Ok(Some(ImportedRule {
+        conditions,
+        action,
+        // set by the caller, which counts the rules of the chain
+        location: String::new(),
+        interfaces,
+    }))
+}
+
+/// Splits the value of `--to-source` and `--to-destination`, written as
+/// `addr[-addr][:port]` or `[addr6]:port`.
+fn nat_target(to: &str) -> NatTarget {
+    let (addr, port) = match to.strip_prefix('[') {
+        Some(rest) => rest
+            .split_once("]:")
+            .unwrap_or((rest.trim_end_matches(']'), "")),
+        // more than one colon is an IPv6 address without a port
+        None => match to.split_once(':') {
+            Some((addr, port)) if !port.contains(':') => (addr, port),
+            _ => (to, ""),
+        },
+    };
+    NatTarget {
+        addr: addr.into(),
+        port: port.into(),
+        ..Default::default()
+    }
+}
+
+/// Translates the `--reject-with` types of iptables and ip6tables.
+fn reject_reason(reject_with: &str) -> anyhow::Result<String> {
+    let reason = match reject_with {
+        "tcp-reset" => "tcp reset",
+        "icmp-net-unreachable" | "net-unreach" => "net-unreachable",
+        "icmp-host-unreachable" | "host-unreach" => "host-unreachable",
+        "icmp-port-unreachable" | "port-unreach" | "icmp6-port-unreachable" => "port-unreachable",
+        "icmp-proto-unreachable" | "proto-unreach" => "prot-unreachable",
+        "icmp-net-prohibited" | "net-prohib" => "net-prohibited",
+        "icmp-host-prohibited" | "host-prohib" => "host-prohibited",
+        "icmp-admin-prohibited" | "admin-prohib" | "icmp6-adm-prohibited" | "adm-prohibited" => {
+            "admin-prohibited"
+        }
+        "icmp6-no-route" | "no-route" => "no-route",
+        "icmp6-addr-unreachable" | "addr-unreach" => "addr-unreachable",
+        reject_with => {
+            return Err(anyhow::anyhow!(
+                "`--reject-with {reject_with}` is not supported"
+            ))
+        }
+    };
+    Ok(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::{Graph, NodeTemplateTrait};
+
+    use super::*;
+    use crate::import::build_graph;
+    use crate::nf_graph::simulate::{self, PacketFields, Verdict};
+    use crate::nf_graph::NFGraphState;
+    use crate::Evaluator;
+
+    const RULESET: &str = r#"# Generated by iptables-save v1.8.7 on Mon Oct 12 10:00:00 2026
+*filter
+:INPUT DROP [0:0]
+:FORWARD DROP [0:0]
+:OUTPUT ACCEPT [0:0]
+:SERVICES - [0:0]
+-A INPUT -i lo -j ACCEPT
+-A INPUT -m conntrack --ctstate RELATED,ESTABLISHED -j ACCEPT
+-A INPUT -s 10.0.0.0/8 -j SERVICES
+-A INPUT -j LOG --log-prefix "dropped: "
+-A SERVICES ! -i eth+ -p tcp -m multiport --dports 22,8000:8080 -m comment --comment "ssh and web" -j ACCEPT
+-A FORWARD -i eth1 -o eth0 -j ACCEPT
+-A FORWARD -p tcp -j REJECT --reject-with tcp-reset
+COMMIT
+*nat
+:PREROUTING ACCEPT [0:0]
+:INPUT ACCEPT [0:0]
+:OUTPUT ACCEPT [0:0]
+:POSTROUTING ACCEPT [0:0]
+-A PREROUTING -i eth0 -p tcp --dport 80 -j DNAT --to-destination 192.168.1.10:8080
+-A POSTROUTING -o eth0 -j MASQUERADE --random
+COMMIT
+"#;
+
+    #[test]
+    fn test_parse_iptables_save() {
+        let ruleset = parse_iptables_save(RULESET).unwrap();
+        assert_eq!(
+            ruleset.unsupported,
+            [
+                "filter INPUT, rule 4: rule without a verdict is left out",
+                "Chain `INPUT` of the `nat` table",
+                "Chain `OUTPUT` of the `nat` table",
+            ]
+        );
+
+        let services = &ruleset.chains["filter SERVICES"].rules[0];
+        let conditions: Vec<_> = services
+            .conditions
+            .iter()
+            .map(|condition| (format!("{:?}", condition.node), condition.pass))
+            .collect();
+        assert_eq!(
+            conditions,
+            [
+                ("InterfaceFilter(\"eth*\")".to_string(), "non-match"),
+                ("ProtocolFilter(\"tcp\")".to_string(), "match"),
+                (
+                    "DestinationPortFilter(\"22,8000-8080\")".to_string(),
+                    "match"
+                ),
+            ]
+        );
+
+        let input = &ruleset.base_chains[&BaseChain::FilterInput];
+        assert_eq!(input.policy, Some(NfChainPolicy::Drop));
+        assert_eq!(input.family, NFFamily::IPv4);
+        assert_eq!(input.rules.len(), 3);
+        assert!(matches!(
+            &input.rules[1].conditions[0].node,
+            NFNodeData::CtStateFilter(states) if states == &["related", "established"]
+        ));
+        assert!(matches!(
+            &input.rules[2].action,
+            Action::Jump(chain) if chain == "filter SERVICES"
+        ));
+        assert!(matches!(
+            &ruleset.base_chains[&BaseChain::FilterForward].rules[1].action,
+            Action::Reject(reason) if reason == "tcp reset"
+        ));
+        assert!(matches!(
+            &ruleset.base_chains[&BaseChain::NatPrerouting].rules[0].action,
+            Action::Nat(NFNodeData::DestinationNAT(target))
+                if target.addr == "192.168.1.10" && target.port == "8080"
+        ));
+        assert!(matches!(
+            &ruleset.base_chains[&BaseChain::NatPostrouting].rules[0].action,
+            Action::Nat(NFNodeData::Masquerade(target)) if target.random
+        ));
+    }
+
+    #[test]
+    fn test_interface_options_follow_chain() {
+        let ruleset = parse_iptables_save(
+            "*filter
+:INPUT ACCEPT [0:0]
+:FORWARD DROP [0:0]
+:OUTPUT ACCEPT [0:0]
+-A INPUT -i eth0 -j ACCEPT
+-A INPUT -o eth0 -j DROP
+-A FORWARD -i eth1 -o eth0 -j ACCEPT
+-A FORWARD -o eth1 -j ACCEPT
+COMMIT
+",
+        )
+        .unwrap();
+        let mut graph = Graph::new();
+        let mut user_state = NFGraphState::default();
+        let mut nodes = vec![];
+        for node in [NFNodeData::Source, NFNodeData::Localhost] {
+            let label = node.node_graph_label(&mut user_state);
+            nodes.push(graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            }));
+        }
+        let unsupported = build_graph(&ruleset, &mut graph, &mut user_state, nodes[0], nodes[1]);
+        for left_out in [
+            "filter INPUT, rule 2: rule matching `-o` is left out for incoming packets, \
+             where Interface Filters match the incoming interface",
+            "filter FORWARD, rule 1: rule matching `-i` is left out for forwarded and \
+             outgoing packets, where Interface Filters match the outgoing interface",
+        ] {
+            assert!(unsupported.iter().any(|message| message == left_out));
+        }
+        let interfaces: Vec<_> = graph
+            .iter_nodes()
+            .filter_map(|node| match &graph[node].user_data {
+                NFNodeData::InterfaceFilter(interface) => Some(interface.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(interfaces, ["eth0", "eth1"]);
+    }
+
+    #[test]
+    fn test_forward_chain_sees_every_forwarded_packet() {
+        let ruleset = parse_iptables_save(
+            "*filter
+:INPUT DROP [0:0]
+:FORWARD DROP [0:0]
+:OUTPUT ACCEPT [0:0]
+-A INPUT -m conntrack --ctstate ESTABLISHED -j ACCEPT
+-A FORWARD -p tcp --dport 80 -j ACCEPT
+COMMIT
+",
+        )
+        .unwrap();
+        let mut graph = Graph::new();
+        let mut user_state = NFGraphState::default();
+        let mut nodes = vec![];
+        for node in [NFNodeData::Source, NFNodeData::Localhost] {
+            let label = node.node_graph_label(&mut user_state);
+            nodes.push(graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            }));
+        }
+        build_graph(&ruleset, &mut graph, &mut user_state, nodes[0], nodes[1]);
+
+        // the established rule of the input chain leaves forwarded packets
+        // to the forward chain, and its policy
+        let trace = |destination: &str, oifname: &str| {
+            let fields = PacketFields {
+                protocol: "tcp".into(),
+                source: "198.51.100.7:40000".into(),
+                destination: destination.into(),
+                oifname: oifname.into(),
+                ct_state: "established".into(),
+                ..Default::default()
+            };
+            simulate::trace(
+                &graph,
+                nodes[0],
+                &user_state.chains,
+                None,
+                fields.packet().unwrap(),
+            )
+        };
+        let forwarded = trace("192.168.1.10:80", "eth1");
+        assert_eq!(forwarded.verdict, Verdict::Accept);
+        let labels: Vec<_> = forwarded
+            .steps
+            .iter()
+            .map(|step| step.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "Incoming Source",
+                "Router",
+                "Family Splitter",
+                "Protocol Filter",
+                "Destination Port Filter",
+                "Accept"
+            ]
+        );
+        assert_eq!(
+            trace("192.168.1.10:22", "eth1").verdict,
+            Verdict::Policy(BaseChain::FilterForward, NfChainPolicy::Drop)
+        );
+        assert_eq!(trace("192.0.2.1:22", "").verdict, Verdict::Accept);
+
+        let evaluator = Evaluator::new(&graph, &user_state.chains, None);
+        let (paths, diagnostics) = evaluator.paths().unwrap();
+        assert!(diagnostics.is_empty());
+        let forward_paths: Vec<_> = paths
+            .iter()
+            .filter(|path| {
+                path.iter()
+                    .any(|p| p.variant == "core:router" && p.params["route"] == "forwarded")
+            })
+            .collect();
+        // the port 80 rule, and IPv6 packets bypassing the IPv4 table
+        assert_eq!(forward_paths.len(), 2);
+        for path in forward_paths {
+            assert!(path.iter().all(|p| p.variant != "core:ct_state_filter"));
+        }
+    }
+}
@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+
+use nftables::expr::{Expression, MetaKey, NamedExpression, Payload, PayloadField, SetItem};
+use nftables::schema::{NfListObject, NfObject};
+use nftables::stmt::{Match, NATFlag, Operator, Reject, RejectType, Statement, NAT};
+use nftables::types::NfFamily;
+use serde::Serialize;
+
+use super::{Action, Condition, ImportedChain, ImportedRule, InterfaceOption, Ruleset};
+use crate::compile::BaseChain;
+use crate::nf_graph::{params, NFFamily, NFNodeData, NatTarget};
+
+/// Base chains the graph can be built from.
+const IMPORTED_BASE_CHAINS: [BaseChain; 5] = [
+    BaseChain::NatPrerouting,
+    BaseChain::FilterInput,
+    BaseChain::FilterForward,
+    BaseChain::FilterOutput,
+    BaseChain::NatPostrouting,
+];
+
+/// Reads the output of `nft -j list ruleset`.
+pub fn parse_nft_json(json: &str) -> anyhow::Result<Ruleset> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| anyhow::anyhow!("Cannot read nft JSON: {e}"))?;
+    let objects = value
+        .get("nftables")
+        .and_then(serde_json::Value::as_array)
+        .ok_or(anyhow::anyhow!("Not an `nft -j list ruleset` output"))?;
+
+    let mut ruleset = Ruleset::default();
+    // objects are read one by one, so a single object the schema does not
+    // know about is reported instead of failing the whole import
+    let mut list_objects = vec![];
+    for object in objects {
+        match serde_json::from_value::<NfObject>(object.clone()) {
+            Ok(NfObject::ListObject(object)) => list_objects.push(object),
+            Ok(NfObject::CmdObject(_)) => ruleset.unsupported.push(
+                "Commands are not supported, export the ruleset with `nft -j list ruleset`".into(),
+            ),
+            Err(_) => ruleset
+                .unsupported
+                .push(format!("Cannot read `{}`", kind(object))),
+        }
+    }
+
+    // base chains by their table and name, as rules refer to them
+    let mut base_names = BTreeMap::new();
+    for object in &list_objects {
+        let NfListObject::Chain(chain) = object else {
+            continue;
+        };
+        let Some(family) = family(&chain.family) else {
+            ruleset.unsupported.push(format!(
+                "Chain `{}` of a {:?} table",
+                chain.name, chain.family
+            ));
+            continue;
+        };
+        let imported = ImportedChain {
+            rules: vec![],
+            priority: chain.prio,
+            policy: chain.policy.clone(),
+            family,
+        };
+        let key = chain_key(&chain.family, &chain.table, &chain.name);
+        let (Some(chain_type), Some(hook)) = (&chain._type, &chain.hook) else {
+            ruleset.chains.insert(key, imported);
+            continue;
+        };
+        match BaseChain::from_hook(chain_type, hook) {
+            Some(base) if IMPORTED_BASE_CHAINS.contains(&base) => {
+                if let Some((existing, _)) = base_names.iter().find(|(_, b)| **b == base) {
+                    ruleset.unsupported.push(format!(
+                        "Chain `{}` hooks like `{existing}`, only the first one is imported",
+                        chain.name
+                    ));
+                    continue;
+                }
+                base_names.insert(key, base);
+                ruleset.base_chains.insert(base, imported);
+            }
+            _ => ruleset.unsupported.push(format!(
+                "Chain `{}` of type {chain_type:?} on the {hook:?} hook",
+                chain.name
+            )),
+        }
+    }
+
+    for object in list_objects {
+        let NfListObject::Rule(rule) = object else {
+            if !matches!(
+                object,
+                NfListObject::Table(_) | NfListObject::Chain(_) | NfListObject::MetainfoObject(_)
+            ) {
+                ruleset
+                    .unsupported
+                    .push(format!("`{}` objects", kind(&object)));
+            }
+            continue;
+        };
+        let key = chain_key(&rule.family, &rule.table, &rule.chain);
+        let chain = match base_names.get(&key) {
+            Some(base) => ruleset.base_chains.get_mut(base),
+            None => ruleset.chains.get_mut(&key),
+        };
+        let Some(chain) = chain else {
+            continue;
+        };
+        let location = format!(
+            "{} {}, rule {}",
+            rule.table,
+            rule.chain,
+            rule.handle
+                .map_or(chain.rules.len() + 1, |handle| handle as usize)
+        );
+        match import_rule(&rule.expr, &mut ruleset.unsupported, &location) {
+            Ok(Some(mut imported)) => {
+                if let Action::Jump(target) = &mut imported.action {
+                    *target = chain_key(&rule.family, &rule.table, target);
+                }
+                chain.rules.push(imported);
+            }
+            Ok(None) => {}
+            Err(err) => ruleset.unsupported.push(format!("{location}: {err}")),
+        }
+    }
+    Ok(ruleset)
+}
+
+/// Jump targets are chain names, which are unique within a table only.
+fn chain_key(family: &NfFamily, table: &str, name: &str) -> String {
+    format!("{} {table} {name}", kind(family))
+}
+
+fn family(family: &NfFamily) -> Option<NFFamily> {
+    match family {
+        NfFamily::IP => Some(NFFamily::IPv4),
+        NfFamily::IP6 => Some(NFFamily::IPv6),
+        NfFamily::INet => Some(NFFamily::Inet),
+        NfFamily::ARP | NfFamily::Bridge | NfFamily::NetDev => None,
+    }
+}
+
+/// Name of the JSON object a value is serialized to, e.g. `counter`.
+fn kind(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Object(object)) => object.keys().next().cloned().unwrap_or_default(),
+        Ok(serde_json::Value::String(value)) => value,
+        Ok(value) => value.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Converts the statements of a rule. Statements that do not change where
+/// packets go are skipped and reported, a rule without a verdict is left out.
+fn import_rule(
+    statements: &[Statement],
+    unsupported: &mut Vec<String>,
+    location: &str,
+) -> anyhow::Result<Option<ImportedRule>> {
+    let mut conditions = vec![];
+    let mut interfaces = vec![];
+    let mut action = None;
+    for statement in statements {
+        let next = match statement {
+            Statement::Match(statement) => {
+                if let Expression::Named(NamedExpression::Meta(meta)) = &statement.left {
+                    if matches!(meta.key, MetaKey::Iifname | MetaKey::Oifname) {
+                        interfaces.push(InterfaceOption {
+                            name: kind(&meta.key),
+                            incoming: meta.key == MetaKey::Iifname,
+                        });
+                    }
+                }
+                conditions.extend(import_match(statement)?);
+                continue;
+            }
+            Statement::Counter(_) | Statement::CounterRef(_) | Statement::Log(_) => {
+                unsupported.push(format!("{location}: `{}` is ignored", kind(statement)));
+                continue;
+            }
+            Statement::Accept(_) => Action::Accept,
+            Statement::Drop(_) => Action::Drop,
+            Statement::Reject(reject) => Action::Reject(reject_reason(reject.as_ref())?),
+            Statement::Return(_) => Action::Return,
+            Statement::Jump(target) => Action::Jump(target.target.clone()),
+            Statement::Goto(target) => {
+                unsupported.push(format!("{location}: `goto` is imported as `jump`"));
+                Action::Jump(target.target.clone())
+            }
+            Statement::SNAT(Some(nat)) => {
+                Action::Nat(NFNodeData::SourceNAT(nat_target(Some(nat), true)?))
+            }
+            Statement::DNAT(Some(nat)) => {
+                Action::Nat(NFNodeData::DestinationNAT(nat_target(Some(nat), true)?))
+            }
+            Statement::Masquerade(nat) => {
+                Action::Nat(NFNodeData::Masquerade(nat_target(nat.as_ref(), false)?))
+            }
+            Statement::Redirect(nat) => {
+                Action::Nat(NFNodeData::Redirect(nat_target(nat.as_ref(), false)?))
+            }
+            statement => {
+                return Err(anyhow::anyhow!(
+                    "`{}` statements are not supported",
+                    kind(statement)
+                ))
+            }
+        };
+        if let Action::Nat(node) = &next {
+            node.validate()?;
+        }
+        if action.replace(next).is_some() {
+            return Err(anyhow::anyhow!(
+                "Rules with several verdicts are not supported"
+            ));
+        }
+    }
+    let Some(action) = action else {
+        unsupported.push(format!("{location}: rule without a verdict is left out"));
+        return Ok(None);
+    };
+    Ok(Some(ImportedRule {
+        conditions,
+        action,
+        location: location.to_string(),
+        interfaces,
+    }))
+}
+
+fn import_match(statement: &Match) -> anyhow::Result<Vec<Condition>> {
+    let negated = match statement.op {
+        Operator::EQ | Operator::IN => false,
+        Operator::NEQ => true,
+        _ => {
+            return Err(anyhow::anyhow!(
+                "`{}` comparisons are not supported",
+                kind(&statement.op)
+            ))
+        }
+    };
+    let filter = || values(&statement.right).map(|values| values.join(", "));
+    let conditions = match &statement.left {
+        Expression::Named(NamedExpression::Payload(Payload::PayloadField(PayloadField {
+            protocol,
+            field,
+        }))) => match (protocol.as_str(), field.as_str()) {
+            ("ip" | "ip6", "saddr") => vec![NFNodeData::SourceAddressFilter(filter()?)],
+            ("ip" | "ip6", "daddr") => vec![NFNodeData::DestinationAddressFilter(filter()?)],
+            ("ip", "protocol") | ("ip6", "nexthdr") => vec![NFNodeData::ProtocolFilter(filter()?)],
+            (protocol, "sport" | "dport")
+                if protocol == "th" || params::PORT_PROTOCOLS.contains(&protocol) =>
+            {
+                let port = if field == "sport" {
+                    NFNodeData::SourcePortFilter(filter()?)
+                } else {
+                    NFNodeData::DestinationPortFilter(filter()?)
+                };
+                // `tcp dport != 22` still only matches TCP packets
+                if protocol != "th" {
+                    let protocol =
+                        Condition::new(NFNodeData::ProtocolFilter(protocol.into()), false);
+                    return Ok(vec![protocol, Condition::checked(port, negated)?]);
+                }
+                vec![port]
+            }
+            (protocol, field) => {
+                return Err(anyhow::anyhow!(
+                    "`{protocol} {field}` matches are not supported"
+                ))
+            }
+        },
+        Expression::Named(NamedExpression::Meta(meta)) => match meta.key {
+            MetaKey::L4proto => vec![NFNodeData::ProtocolFilter(filter()?)],
+            MetaKey::Iifname | MetaKey::Oifname => vec![NFNodeData::InterfaceFilter(filter()?)],
+            MetaKey::Nfproto => {
+                let (pass, fail) = match (filter()?.as_str(), negated) {
+                    ("ipv4", false) | ("ipv6", true) => ("ipv4", "ipv6"),
+                    ("ipv6", false) | ("ipv4", true) => ("ipv6", "ipv4"),
+                    (family, _) => return Err(anyhow::anyhow!("Unknown family `{family}`")),
+                };
+                return Ok(vec![Condition {
+                    node: NFNodeData::FamilySplitter,
+                    pass,
+                    fail,
+                }]);
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "`meta {}` matches are not supported",
+                    kind(&meta.key)
+                ))
+            }
+        },
+        Expression::Named(NamedExpression::CT(ct)) if ct.key == "state" => {
+            vec![NFNodeData::CtStateFilter(params::ct_states(&filter()?)?)]
+        }
+        Expression::Named(NamedExpression::CT(ct)) => {
+            return Err(anyhow::anyhow!("`ct {}` matches are not supported", ct.key))
+        }
+        left => {
+            return Err(anyhow::anyhow!(
+                "`{}` matches are not supported",
+                kind(left)
+            ))
+        }
+    };
+    conditions
+        .into_iter()
+        .map(|node| Condition::checked(node, negated))
+        .collect()
+}
+
+fn values(expression: &Expression) -> anyhow::Result<Vec<String>> {
+    let values = match expression {
+        Expression::String(value) if value.starts_with('@') => {
+            return Err(anyhow::anyhow!("Named sets are not supported"))
+        }
+        Expression::String(value) => vec![value.clone()],
+        Expression::Number(value) => vec![value.to_string()],
+        Expression::Range(range) => match range.range.as_slice() {
+            [first, last] => vec![format!(
+                "{}-{}",
+                values(first)?.concat(),
+                values(last)?.concat()
+            )],
+            _ => return Err(anyhow::anyhow!("Malformed range")),
+        },
+        Expression::Named(NamedExpression::Prefix(prefix)) => {
+            vec![format!("{}/{}", values(&prefix.addr)?.concat(), prefix.len)]
+        }
+        Expression::Named(NamedExpression::Set(items)) => {
+            let mut set = vec![];
+            for item in items {
+                let SetItem::Element(element) = item else {
+                    return Err(anyhow::anyhow!("Maps are not supported"));
+                };
+                set.extend(values(element)?);
+            }
+            set
+        }
+        Expression::List(items) => {
+            let mut list = vec![];
+            for item in items {
+                list.extend(values(item)?);
+            }
+            list
+        }
+        expression => {
+            return Err(anyhow::anyhow!(
+                "`{}` values are not supported",
+                kind(expression)
+            ))
+        }
+    };
+    Ok(values)
+}
+
+fn reject_reason(reject: Option<&Reject>) -> anyhow::Result<String> {
+    let reason = match reject {
+        Some(Reject {
+            _type: Some(RejectType::TCPReset),
+            ..
+        }) => "tcp reset".to_string(),
+        Some(Reject {
+            expr: Some(code), ..
+        }) => kind(code),
+        _ => "port-unreachable".to_string(),
+    };
+    params::reject_reason(&reason)
+}
+
+fn nat_target(nat: Option<&NAT>, needs_addr: bool) -> anyhow::Result<NatTarget> {
+    let Some(nat) = nat else {
+        return Ok(NatTarget::default());
+    };
+    let addr = match &nat.addr {
+        Some(Expression::Range(range)) => match range.range.as_slice() {
+            [first, last] => format!("{}-{}", values(first)?.concat(), values(last)?.concat()),
+            _ => return Err(anyhow::anyhow!("Malformed range")),
+        },
+        Some(addr) => values(addr)?.concat(),
+        None if needs_addr => {
+            return Err(anyhow::anyhow!("NAT without an address is not supported"))
+        }
+        None => String::new(),
+    };
+    let flags = nat.flags.clone().unwrap_or_default();
+    if flags.contains(&NATFlag::FullyRandom) {
+        return Err(anyhow::anyhow!("The `fully-random` flag is not supported"));
+    }
+    Ok(NatTarget {
+        addr,
+        port: nat.port.map(|port| port.to_string()).unwrap_or_default(),
+        persistent: flags.contains(&NATFlag::Persistent),
+        random: flags.contains(&NATFlag::Random),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::{Graph, NodeTemplateTrait};
+
+    use super::*;
+    use crate::import::build_graph;
+    use nftables::types::NfChainPolicy;
+
+    use crate::nf_graph::simulate::{trace, PacketFields, Verdict};
+    use crate::nf_graph::NFGraphState;
+
+    const RULESET: &str = r#"{"nftables": [
+        {"metainfo": {"version": "1.0.9", "release_name": "Old Doc Yak #3", "json_schema_version": 1}},
+        {"table": {"family": "inet", "name": "filter", "handle": 1}},
+        {"chain": {"family": "inet", "table": "filter", "name": "input", "handle": 1,
+            "type": "filter", "hook": "input", "prio": 0, "policy": "drop"}},
+        {"chain": {"family": "inet", "table": "filter", "name": "services", "handle": 2}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 3, "expr": [
+            {"match": {"op": "in", "left": {"ct": {"key": "state"}}, "right": ["established", "related"]}},
+            {"accept": null}]}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "input", "handle": 4, "expr": [
+            {"match": {"op": "==", "left": {"meta": {"key": "iifname"}}, "right": "eth0"}},
+            {"jump": {"target": "services"}}]}},
+        {"rule": {"family": "inet", "table": "filter", "chain": "services", "handle": 5, "expr": [
+            {"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}},
+                "right": {"set": [22, {"range": [8000, 8080]}]}}},
+            {"counter": {"packets": 0, "bytes": 0}},
+            {"accept": null}]}}
+    ]}"#;
+
+    #[test]
+    fn test_import_nft_ruleset() {
+        let ruleset = parse_nft_json(RULESET).unwrap();
+        assert_eq!(
+            ruleset.unsupported,
+            ["filter services, rule 5: `counter` is ignored"]
+        );
+
+        let mut graph = Graph::new();
+        let mut user_state = NFGraphState::default();
+        let mut add_node = |graph: &mut Graph<_, _, _>, node: NFNodeData| {
+            let label = node.node_graph_label(&mut user_state);
+            graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            })
+        };
+        let source = add_node(&mut graph, NFNodeData::Source);
+        let localhost = add_node(&mut graph, NFNodeData::Localhost);
+        let mut user_state = NFGraphState::default();
+        let unsupported = build_graph(&ruleset, &mut graph, &mut user_state, source, localhost);
+        assert_eq!(unsupported, ruleset.unsupported);
+        // Source, Local Machine, the connection state, interface, protocol
+        // and port filters, and Accept for outgoing packets
+        assert_eq!(graph.nodes.len(), 7);
+
+        let fields = PacketFields {
+            protocol: "tcp".into(),
+            source: "10.0.0.5:5555".into(),
+            destination: "192.168.1.10:2222".into(),
+            iifname: "eth0".into(),
+            ..Default::default()
+        };
+        let traced = trace(
+            &graph,
+            source,
+            &user_state.chains,
+            None,
+            fields.packet().unwrap(),
+        );
+        let labels: Vec<_> = traced
+            .steps
+            .iter()
+            .map(|step| step.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "Incoming Source",
+                "Connection State Filter",
+                "Interface Filter",
+                "Protocol Filter",
+                "Destination Port Filter",
+            ]
+        );
+        assert_eq!(
+            traced.verdict,
+            Verdict::Policy(BaseChain::FilterInput, NfChainPolicy::Drop)
+        );
+
+        let established = PacketFields {
+            ct_state: "established".into(),
+            ..fields.clone()
+        };
+        let traced = trace(
+            &graph,
+            source,
+            &user_state.chains,
+            None,
+            established.packet().unwrap(),
+        );
+        assert_eq!(traced.steps[1].note, "match");
+        assert_eq!(traced.verdict, Verdict::Accept);
+
+        let fields = PacketFields {
+            destination: "192.168.1.10:8080".into(),
+            ..fields
+        };
+        let traced = trace(
+            &graph,
+            source,
+            &user_state.chains,
+            None,
+            fields.packet().unwrap(),
+        );
+        assert_eq!(traced.steps.last().unwrap().label, "Accept");
+        assert_eq!(traced.verdict, Verdict::Accept);
+    }
+
+    #[test]
+    fn test_interface_matches_follow_hook() {
+        let ruleset = parse_nft_json(
+            r#"{"nftables": [
+            {"table": {"family": "inet", "name": "filter", "handle": 1}},
+            {"chain": {"family": "inet", "table": "filter", "name": "forward", "handle": 1,
+                "type": "filter", "hook": "forward", "prio": 0, "policy": "drop"}},
+            {"chain": {"family": "inet", "table": "filter", "name": "lan", "handle": 2}},
+            {"rule": {"family": "inet", "table": "filter", "chain": "forward", "handle": 3, "expr": [
+                {"match": {"op": "==", "left": {"meta": {"key": "iifname"}}, "right": "eth0"}},
+                {"accept": null}]}},
+            {"rule": {"family": "inet", "table": "filter", "chain": "forward", "handle": 4, "expr": [
+                {"jump": {"target": "lan"}}]}},
+            {"rule": {"family": "inet", "table": "filter", "chain": "lan", "handle": 5, "expr": [
+                {"match": {"op": "==", "left": {"meta": {"key": "oifname"}}, "right": "eth1"}},
+                {"accept": null}]}}
+        ]}"#,
+        )
+        .unwrap();
+        assert!(ruleset.unsupported.is_empty());
+
+        let mut graph = Graph::new();
+        let mut user_state = NFGraphState::default();
+        let mut nodes = vec![];
+        for node in [NFNodeData::Source, NFNodeData::Localhost] {
+            let label = node.node_graph_label(&mut user_state);
+            nodes.push(graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            }));
+        }
+        let unsupported = build_graph(&ruleset, &mut graph, &mut user_state, nodes[0], nodes[1]);
+        // Interface Filters of forwarded packets only match the outgoing
+        // interface
+        assert_eq!(
+            unsupported.last().unwrap(),
+            "filter forward, rule 3: rule matching `iifname` is left out for forwarded and \
+             outgoing packets, where Interface Filters match the outgoing interface"
+        );
+        let interface_filters = graph
+            .iter_nodes()
+            .filter(|node| matches!(graph[*node].user_data, NFNodeData::InterfaceFilter(_)))
+            .count();
+        assert_eq!(interface_filters, 1);
+    }
+}
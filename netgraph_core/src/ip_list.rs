@@ -0,0 +1,148 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+use ipnetwork::IpNetwork;
+use nftables::schema::{Set, SetFlag, SetType, SetTypeValue};
+use nftables::types::NfFamily;
+
+use crate::compile;
+use crate::nf_graph::NFFamily;
+
+/// Addresses read from a file referenced by an IP File Filter node.
+#[derive(Debug, Clone)]
+pub struct IpList {
+    pub name: String,
+    pub source: String,
+    pub family: NFFamily,
+    pub entries: Vec<IpNetwork>,
+}
+
+impl IpList {
+    /// Reads and validates the list, one address or CIDR network per line.
+    /// Empty lines and `#` comments are skipped. A relative `path` is read
+    /// from `graph_dir`, the directory the graph is saved in, and kept as
+    /// written in the exported set.
+    pub fn read(path: &Path, graph_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let source = path.to_string_lossy().to_string();
+        let path = match graph_dir {
+            Some(graph_dir) => graph_dir.join(path),
+            None => path.to_path_buf(),
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read IP list {source}: {e}"))?;
+        Self::parse(&source, &text)
+    }
+
+    pub fn parse(source: &str, text: &str) -> anyhow::Result<Self> {
+        let mut entries = vec![];
+        let mut errors = vec![];
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.parse::<IpNetwork>() {
+                Ok(net) => entries.push(net),
+                Err(_) => errors.push(format!(
+                    "{source}:{}: `{line}` is not an IP address or network",
+                    line_no + 1
+                )),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(anyhow::anyhow!(errors.join("\n")));
+        }
+
+        let family = match (
+            entries.iter().any(IpNetwork::is_ipv4),
+            entries.iter().any(IpNetwork::is_ipv6),
+        ) {
+            (true, false) => NFFamily::IPv4,
+            (false, true) => NFFamily::IPv6,
+            (true, true) => {
+                return Err(anyhow::anyhow!(
+                    "{source}: IPv4 and IPv6 addresses cannot be mixed in one list"
+                ))
+            }
+            (false, false) => return Err(anyhow::anyhow!("{source}: list contains no addresses")),
+        };
+
+        Ok(Self {
+            name: Self::set_name(source),
+            source: source.to_string(),
+            family,
+            entries: Self::merge(entries),
+        })
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        self.entries.iter().any(|network| network.contains(addr))
+    }
+
+    pub fn to_set(&self) -> Set {
+        let set_type = if self.family == NFFamily::IPv6 {
+            SetType::Ipv6Addr
+        } else {
+            SetType::Ipv4Addr
+        };
+        let elem = self.entries.iter().map(compile::address).collect();
+        Set {
+            family: NfFamily::INet,
+            table: compile::TABLE.into(),
+            name: self.name.clone(),
+            handle: None,
+            set_type: SetTypeValue::Single(set_type),
+            policy: None,
+            flags: Some([SetFlag::Interval].into()),
+            elem: Some(elem),
+            timeout: None,
+            gc_interval: None,
+            size: None,
+            comment: Some(self.source.clone()),
+        }
+    }
+
+    fn set_name(source: &str) -> String {
+        format!("ip_list_{:016x}", compile::stable_hash(source.as_bytes()))
+    }
+
+    // nft rejects overlapping intervals, so networks already covered by a
+    // wider one are dropped
+    fn merge(mut entries: Vec<IpNetwork>) -> Vec<IpNetwork> {
+        entries.sort_by_key(|net| (net.network(), net.prefix()));
+        let mut merged: Vec<IpNetwork> = vec![];
+        for net in entries {
+            if merged
+                .last()
+                .is_some_and(|last| last.contains(net.network()))
+            {
+                continue;
+            }
+            merged.push(net);
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ip_list() {
+        let list = IpList::parse(
+            "list.txt",
+            "# office\n10.0.0.0/8\n10.1.0.0/16\n\n192.168.1.10 # printer\n",
+        )
+        .unwrap();
+        assert_eq!(list.family, NFFamily::IPv4);
+        assert_eq!(list.entries.len(), 2);
+
+        let err = IpList::parse("list.txt", "10.0.0.1\nfoo\n::1").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "list.txt:2: `foo` is not an IP address or network"
+        );
+        assert!(IpList::parse("list.txt", "10.0.0.1\n::1").is_err());
+    }
+}
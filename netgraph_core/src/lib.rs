@@ -0,0 +1,60 @@
+//! Graph model of netgraph and its compilation into nftables rulesets,
+//! usable without the editor.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use slotmap::Key;
+
+use egui_node_graph::{Graph, GraphEditorState, NodeId};
+
+pub use diagnostic::Diagnostic;
+pub use evaluate::Evaluator;
+pub use storage::SavedGraph;
+
+use nf_graph::{DataType, NFGraphState, NFNodeData, ValueType};
+
+pub mod bundle;
+pub mod compile;
+pub mod diagnostic;
+pub mod evaluate;
+pub mod import;
+pub mod ip_list;
+pub mod nf_graph;
+pub mod plugin;
+pub mod storage;
+pub mod topology;
+
+pub type NFGraph = Graph<NFNodeData, DataType, ValueType>;
+pub type NFEditorState =
+    GraphEditorState<NFNodeData, DataType, ValueType, NFNodeData, NFGraphState>;
+
+/// What a node requires of the packets passing through one of its outputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Predicate {
+    pub variant: String,
+    pub params: BTreeMap<String, String>,
+    /// Identity of the graph node the predicate was produced by, used to
+    /// derive stable chain names. Plugin scripts may leave it out.
+    #[serde(default)]
+    pub node: String,
+}
+/// The predicates of the nodes along a path, starting at Source.
+pub type PredicateSet = Vec<Predicate>;
+pub type NodeOutputs = BTreeMap<String, Vec<PredicateSet>>;
+pub type NodeOutputDB = BTreeMap<NodeId, NodeOutputs>;
+
+impl Predicate {
+    pub fn new(variant: String, node_id: NodeId, params: BTreeMap<String, String>) -> Self {
+        Self {
+            variant,
+            params,
+            node: node_key(node_id),
+        }
+    }
+}
+
+/// Identity of a node as recorded in predicates, e.g. `3v1`.
+pub fn node_key(node_id: NodeId) -> String {
+    format!("{:?}", node_id.data())
+}
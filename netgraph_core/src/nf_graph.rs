@@ -0,0 +1,32 @@
+pub use data_type::{DataType, NFDirection, NFFamily};
+pub use graph_state::{NFGraphState, ValidationCache};
+pub use limit::LimitOptions;
+pub use log::LogOptions;
+pub use mark::{MarkOptions, RouteOptions};
+pub use nat::{Backend, LoadBalanceTarget, NatTarget};
+pub use node_data::NFNodeData;
+pub use node_template_iter::NodeTemplateIter;
+#[cfg(feature = "ui")]
+pub use response::NodeResponse;
+pub use tcp_flags::TcpFlagsOptions;
+pub use time::TimeOptions;
+pub use value_type::ValueType;
+
+mod data_type;
+mod graph_state;
+mod limit;
+mod log;
+mod mark;
+mod nat;
+pub mod node_data;
+mod node_template;
+mod node_template_iter;
+pub mod params;
+#[cfg(feature = "ui")]
+mod response;
+pub mod simulate;
+mod tcp_flags;
+mod time;
+#[cfg(feature = "ui")]
+mod ui;
+mod value_type;
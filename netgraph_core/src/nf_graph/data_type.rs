@@ -0,0 +1,38 @@
+use derive_more::Constructor;
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NFFamily {
+    #[default]
+    Inet,
+    IPv4,
+    IPv6,
+}
+
+#[derive(Default, Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum NFDirection {
+    #[default]
+    Either,
+    Incoming,
+    Outgoing,
+    Forwarded,
+}
+
+#[derive(Constructor, Serialize, Deserialize)]
+pub struct DataType {
+    pub(super) family: NFFamily,
+    pub direction: NFDirection,
+}
+
+impl PartialEq for DataType {
+    fn eq(&self, other: &Self) -> bool {
+        (self.direction == NFDirection::Either
+            || other.direction == NFDirection::Either
+            || self.direction == other.direction)
+            && (self.family == NFFamily::Inet
+                || other.family == NFFamily::Inet
+                || self.family == other.family)
+    }
+}
+
+impl Eq for DataType {}
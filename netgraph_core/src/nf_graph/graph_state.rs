@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use egui_node_graph::NodeId;
+
+use super::NFNodeData;
+use crate::compile::ChainSettings;
+use crate::plugin::NFNode;
+use crate::NFGraph;
+
+#[derive(Debug, Clone, Default)]
+pub struct NFGraphState {
+    pub plugins: HashMap<String, HashMap<String, NFNode>>,
+    pub active_node: Option<NodeId>,
+    pub chains: ChainSettings,
+    pub validation: ValidationCache,
+}
+
+/// Results of [`NFNodeData::validate`] by node, with the parameters they
+/// were found for. Validating may read `/etc/passwd` and `/etc/group` or
+/// look for files, which is too slow to repeat for every frame, so a node
+/// is only validated again once its parameters change.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationCache {
+    results: HashMap<NodeId, (NFNodeData, Option<String>)>,
+}
+
+impl ValidationCache {
+    /// The reason the parameters of the node are invalid, if they are.
+    pub fn error(&mut self, node_id: NodeId, node: &NFNodeData) -> Option<&str> {
+        let stale = self
+            .results
+            .get(&node_id)
+            .is_none_or(|(validated, _)| validated != node);
+        if stale {
+            let error = node.validate().err().map(|err| err.to_string());
+            self.results.insert(node_id, (node.clone(), error));
+        }
+        self.results[&node_id].1.as_deref()
+    }
+
+    /// Nodes of `graph` whose parameters are invalid, with the reason. Nodes
+    /// no longer in the graph are forgotten.
+    pub fn invalid_nodes(&mut self, graph: &NFGraph) -> Vec<(NodeId, String)> {
+        self.results
+            .retain(|node_id, _| graph.nodes.contains_key(*node_id));
+        graph
+            .nodes
+            .iter()
+            .filter_map(|(node_id, node)| {
+                let err = self.error(node_id, &node.user_data)?;
+                Some((node_id, err.to_string()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeTemplateTrait;
+
+    use super::*;
+
+    #[test]
+    fn test_validation_follows_parameters() {
+        let mut graph = NFGraph::new();
+        let mut user_state = NFGraphState::default();
+        let mut add_node = |graph: &mut NFGraph, node: NFNodeData| {
+            let label = node.node_graph_label(&mut user_state);
+            graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            })
+        };
+        let ports = add_node(&mut graph, NFNodeData::DestinationPortFilter("22".into()));
+        let list = add_node(&mut graph, NFNodeData::FileIpList(None));
+        let mut cache = ValidationCache::default();
+        assert_eq!(
+            cache.invalid_nodes(&graph),
+            [(list, "IP List file is required".to_string())]
+        );
+
+        graph[ports].user_data = NFNodeData::DestinationPortFilter("80-22".into());
+        assert!(cache.error(ports, &graph[ports].user_data).is_some());
+        graph[ports].user_data = NFNodeData::DestinationPortFilter("22, 8000-8080".into());
+        assert_eq!(cache.error(ports, &graph[ports].user_data), None);
+
+        graph.remove_node(list);
+        assert!(cache.invalid_nodes(&graph).is_empty());
+        assert_eq!(cache.results.len(), 1);
+    }
+
+    #[test]
+    fn test_validate_parameters() {
+        let invalid = [
+            NFNodeData::SourceAddressFilter("10.0.0.0/33".into()),
+            NFNodeData::ProtocolFilter("".into()),
+            NFNodeData::Quota("500 gbytes".into()),
+            NFNodeData::CgroupFilter("system.slice//nginx.service".into()),
+            NFNodeData::SocketUserFilter("no-such-user-of-netgraph".into()),
+            NFNodeData::Counter("with space".into()),
+            NFNodeData::FileIpList(Some("/nonexistent/list.txt".into())),
+        ];
+        for node in invalid {
+            assert!(node.validate().is_err(), "{node:?}");
+        }
+        let valid = [
+            NFNodeData::SourceAddressFilter("10.0.0.0/8, fd00::1".into()),
+            NFNodeData::Quota("500 mbytes".into()),
+            NFNodeData::CgroupFilter("/system.slice/nginx.service".into()),
+            NFNodeData::SocketUserFilter("0, 1000".into()),
+            NFNodeData::Counter("".into()),
+            NFNodeData::Source,
+        ];
+        for node in valid {
+            assert!(node.validate().is_ok(), "{node:?}");
+        }
+    }
+}
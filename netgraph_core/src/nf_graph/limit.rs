@@ -0,0 +1,40 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the Rate Limit node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitOptions {
+    /// Packets or bytes per period, e.g. `10/second` or `2 mbytes/second`.
+    pub rate: String,
+    /// Packets or bytes allowed on top of the rate in a short burst, empty
+    /// for the nft default.
+    pub burst: String,
+    /// Keeps a separate limit for every source address.
+    pub per_source: bool,
+}
+
+impl LimitOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        params::rate(&self.rate)?;
+        params::burst(&self.burst)?;
+        Ok(())
+    }
+}
+
+impl Display for LimitOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.rate.trim())?;
+        match self.burst.trim() {
+            "" => {}
+            burst => write!(f, ", burst {burst}")?,
+        }
+        if self.per_source {
+            write!(f, " per source")?;
+        }
+        Ok(())
+    }
+}
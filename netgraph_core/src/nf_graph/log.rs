@@ -0,0 +1,62 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the Log node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogOptions {
+    pub prefix: String,
+    /// Syslog level, one of [`params::LOG_LEVELS`].
+    pub level: String,
+    /// Netlink group for `ulogd` and similar, empty to log to syslog.
+    pub group: String,
+    /// Most packets logged, e.g. `10/minute`, empty for all of them.
+    pub rate: String,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        LogOptions {
+            prefix: String::new(),
+            level: "warn".into(),
+            group: String::new(),
+            rate: String::new(),
+        }
+    }
+}
+
+impl LogOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        params::log_prefix(&self.prefix)?;
+        if params::log_group(&self.group)?.is_none()
+            && !params::LOG_LEVELS.contains(&self.level.as_str())
+        {
+            return Err(anyhow::anyhow!("`{}` is not a log level", self.level));
+        }
+        if !self.rate.trim().is_empty() {
+            params::rate(&self.rate)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LogOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.prefix.is_empty() {
+            write!(f, "no prefix")?;
+        } else {
+            write!(f, "\"{}\"", self.prefix)?;
+        }
+        match self.group.trim() {
+            "" => write!(f, ", {}", self.level)?,
+            group => write!(f, ", group {group}")?,
+        }
+        match self.rate.trim() {
+            "" => Ok(()),
+            rate => write!(f, ", at most {rate}"),
+        }
+    }
+}
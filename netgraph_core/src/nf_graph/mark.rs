@@ -0,0 +1,95 @@
+use std::fmt::Display;
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the Set Mark and Mark Filter nodes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarkOptions {
+    /// Mark in decimal or as `0x` hex.
+    pub mark: String,
+    /// Uses the mark of the connection instead of the packet.
+    pub conntrack: bool,
+}
+
+impl MarkOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        params::mark(&self.mark).map(|_| ())
+    }
+
+    /// The mark key, as written in nft rules.
+    pub fn key(&self) -> &'static str {
+        if self.conntrack {
+            "ct"
+        } else {
+            "meta"
+        }
+    }
+}
+
+impl Display for MarkOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} mark {}", self.key(), self.mark.trim())
+    }
+}
+
+/// Parameters of the Route Via node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RouteOptions {
+    /// Packet mark the routing rule selects the table by.
+    pub mark: String,
+    /// Routing table holding the default route.
+    pub table: String,
+    /// Next hop of the default route, empty for point-to-point links.
+    pub gateway: String,
+    /// Interface of the default route, empty to find it by the gateway.
+    pub device: String,
+}
+
+impl RouteOptions {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if params::mark(&self.mark)? == 0 {
+            return Err(anyhow::anyhow!("Mark 0 is carried by unmarked packets"));
+        }
+        params::route_table(&self.table)?;
+        self.gateway()?;
+        if !self.device.trim().is_empty() {
+            match params::interfaces(&self.device)?.as_slice() {
+                [device] if !device.ends_with('*') => {}
+                _ => return Err(anyhow::anyhow!("Enter a single interface")),
+            }
+        }
+        if self.gateway.trim().is_empty() && self.device.trim().is_empty() {
+            return Err(anyhow::anyhow!("Enter a gateway, an interface or both"));
+        }
+        Ok(())
+    }
+
+    pub fn gateway(&self) -> anyhow::Result<Option<IpAddr>> {
+        match self.gateway.trim() {
+            "" => Ok(None),
+            gateway => gateway
+                .parse()
+                .map(Some)
+                .map_err(|_| anyhow::anyhow!("`{gateway}` is not an address")),
+        }
+    }
+}
+
+impl Display for RouteOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "table {}", self.table.trim())?;
+        match self.gateway.trim() {
+            "" => {}
+            gateway => write!(f, " via {gateway}")?,
+        }
+        match self.device.trim() {
+            "" => Ok(()),
+            device => write!(f, " dev {device}"),
+        }
+    }
+}
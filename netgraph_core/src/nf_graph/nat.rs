@@ -0,0 +1,201 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the NAT nodes. Masquerade and Redirect leave `addr` empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedNatTarget")]
+pub struct NatTarget {
+    /// Address or `first-last` address range to translate to.
+    pub addr: String,
+    /// Port to translate to, empty to keep the port of the packet.
+    pub port: String,
+    /// Gives a client the same address for every connection.
+    pub persistent: bool,
+    /// Picks a random port for every connection.
+    pub random: bool,
+}
+
+/// Graphs saved before the address and port had separate fields store
+/// the target as a single `addr:port` string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedNatTarget {
+    Endpoint(String),
+    Fields {
+        #[serde(default)]
+        addr: String,
+        #[serde(default)]
+        port: String,
+        #[serde(default)]
+        persistent: bool,
+        #[serde(default)]
+        random: bool,
+    },
+}
+
+impl From<SavedNatTarget> for NatTarget {
+    fn from(saved: SavedNatTarget) -> Self {
+        match saved {
+            SavedNatTarget::Endpoint(target) => NatTarget::from_endpoint(&target),
+            SavedNatTarget::Fields {
+                addr,
+                port,
+                persistent,
+                random,
+            } => NatTarget {
+                addr,
+                port,
+                persistent,
+                random,
+            },
+        }
+    }
+}
+
+impl NatTarget {
+    /// Splits `addr`, `addr:port` or `[addr6]:port` into the address and
+    /// port fields. Anything else is kept as the address, so it is reported
+    /// by [`NatTarget::validate`].
+    pub fn from_endpoint(target: &str) -> Self {
+        let (addr, port) = match params::endpoint(target) {
+            Ok((addr, port)) => (addr.to_string(), port.map(|p| p.to_string())),
+            Err(_) => (target.trim().to_string(), None),
+        };
+        NatTarget {
+            addr,
+            port: port.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks the fields, requiring an address for Source and Destination
+    /// NAT and a port for Redirect.
+    pub fn validate(&self, needs_addr: bool, needs_port: bool) -> anyhow::Result<()> {
+        if needs_addr {
+            params::nat_address(&self.addr)?;
+        }
+        if params::nat_port(&self.port)?.is_none() && needs_port {
+            return Err(anyhow::anyhow!("Port is required"));
+        }
+        if self.persistent && self.random {
+            return Err(anyhow::anyhow!("Pick either persistent or random"));
+        }
+        Ok(())
+    }
+
+    /// The flag set, as written in nft rules.
+    pub fn flags(&self) -> &'static str {
+        match (self.persistent, self.random) {
+            (true, _) => "persistent",
+            (false, true) => "random",
+            (false, false) => "",
+        }
+    }
+}
+
+impl Display for NatTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let addr = self.addr.trim();
+        let port = self.port.trim();
+        let target = match (addr, port) {
+            ("", "") => String::new(),
+            ("", port) => format!("port {port}"),
+            (addr, "") => addr.to_string(),
+            (addr, port) if addr.contains(':') => format!("[{addr}]:{port}"),
+            (addr, port) => format!("{addr}:{port}"),
+        };
+        let parts: Vec<_> = [target.as_str(), self.flags()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Parameters of the Load Balance NAT node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadBalanceTarget {
+    pub backends: Vec<Backend>,
+    /// Picks the backend by a hash of the source address, so a client
+    /// keeps its backend, instead of taking turns.
+    pub hashed: bool,
+    /// Port every backend listens on, empty to keep the port of the packet.
+    pub port: String,
+}
+
+/// An address packets are distributed to, getting `weight` shares of them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Backend {
+    pub addr: String,
+    pub weight: u32,
+}
+
+impl Default for LoadBalanceTarget {
+    fn default() -> Self {
+        LoadBalanceTarget {
+            backends: vec![Backend::default()],
+            hashed: false,
+            port: String::new(),
+        }
+    }
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend {
+            addr: String::new(),
+            weight: 1,
+        }
+    }
+}
+
+impl LoadBalanceTarget {
+    /// The backends as written in the predicate of the node, e.g.
+    /// `10.0.0.2 3, 10.0.0.3 1`.
+    pub fn backend_list(&self) -> String {
+        self.backends
+            .iter()
+            .map(|backend| format!("{} {}", backend.addr.trim(), backend.weight))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self
+            .backends
+            .iter()
+            .any(|backend| backend.addr.trim().is_empty())
+        {
+            return Err(anyhow::anyhow!("Backend address is required"));
+        }
+        params::backends(&self.backend_list())?;
+        params::nat_port(&self.port)?;
+        Ok(())
+    }
+
+    /// The distribution, as named in the predicate of the node.
+    pub fn distribution(&self) -> &'static str {
+        if self.hashed {
+            "hash"
+        } else {
+            "round-robin"
+        }
+    }
+}
+
+impl Display for LoadBalanceTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.backends.len();
+        let plural = if count == 1 { "" } else { "s" };
+        write!(f, "{count} backend{plural}")?;
+        match self.port.trim() {
+            "" => {}
+            port => write!(f, " on port {port}")?,
+        }
+        write!(f, ", {}", self.distribution())
+    }
+}
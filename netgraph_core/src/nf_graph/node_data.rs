@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use derive_more::IsVariant;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    params, LimitOptions, LoadBalanceTarget, LogOptions, MarkOptions, NatTarget, RouteOptions,
+    TcpFlagsOptions, TimeOptions,
+};
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, IsVariant)]
+pub enum NFNodeData {
+    // intermediate nodes
+    FileIpList(Option<PathBuf>),
+    SourceAddressFilter(String),
+    DestinationAddressFilter(String),
+    SourcePortFilter(String),
+    DestinationPortFilter(String),
+    ProtocolFilter(String),
+    TcpFlagsFilter(TcpFlagsOptions),
+    IcmpTypeFilter(Vec<String>),
+    FamilySplitter,
+    InterfaceFilter(String),
+    CtStateFilter(Vec<String>),
+    MarkFilter(MarkOptions),
+    TimeFilter(TimeOptions),
+    // outgoing filters matching the socket of the sending process
+    SocketUserFilter(String),
+    SocketGroupFilter(String),
+    CgroupFilter(String),
+    RateLimit(LimitOptions),
+    /// Amount of data, e.g. `500 mbytes`, after which packets leave
+    /// through the `over quota` output.
+    Quota(String),
+    // pass-through nodes
+    Log(LogOptions),
+    /// Counts the packets passing, in a named counter object if given.
+    Counter(String),
+    SetMark(MarkOptions),
+    // terminal nodes
+    Source,                            // start incoming
+    DestinationNAT(NatTarget),         // terminal for incoming
+    LoadBalanceNAT(LoadBalanceTarget), // terminal for incoming
+    Redirect(NatTarget),               // terminal for incoming
+    Localhost,                         // terminal incoming start outgoing
+    Router,                            // terminal incoming start forwarded and local
+    SourceNAT(NatTarget),              // terminal for outgoing
+    Masquerade(NatTarget),             // terminal for outgoing
+    Drop,                              // terminal for outgoing
+    Reject(String),                    // terminal for outgoing
+    Accept,                            // terminal for outgoing
+    RouteVia(RouteOptions),            // terminal for incoming and outgoing
+    Custom {
+        plugin: String,
+        id: String,
+        data: HashMap<String, String>,
+    },
+}
+
+impl NFNodeData {
+    pub fn get_id(&self) -> String {
+        match self {
+            NFNodeData::Source => "core:source".into(),
+            NFNodeData::FileIpList(_) => "core:file_ip_list".into(),
+            NFNodeData::SourceAddressFilter(_) => "core:source_address_filter".into(),
+            NFNodeData::DestinationAddressFilter(_) => "core:destination_address_filter".into(),
+            NFNodeData::SourcePortFilter(_) => "core:source_port_filter".into(),
+            NFNodeData::DestinationPortFilter(_) => "core:destination_port_filter".into(),
+            NFNodeData::ProtocolFilter(_) => "core:protocol_filter".into(),
+            NFNodeData::TcpFlagsFilter(_) => "core:tcp_flags_filter".into(),
+            NFNodeData::IcmpTypeFilter(_) => "core:icmp_type_filter".into(),
+            NFNodeData::FamilySplitter => "core:family_splitter".into(),
+            NFNodeData::SourceNAT(_) => "core:source_nat".into(),
+            NFNodeData::DestinationNAT(_) => "core:destination_nat".into(),
+            NFNodeData::LoadBalanceNAT(_) => "core:load_balance_nat".into(),
+            NFNodeData::Masquerade(_) => "core:masquerade".into(),
+            NFNodeData::Redirect(_) => "core:redirect".into(),
+            NFNodeData::Drop => "core:drop".into(),
+            NFNodeData::Reject(_) => "core:reject".into(),
+            NFNodeData::Accept => "core:accept".into(),
+            NFNodeData::Custom { plugin, id, .. } => plugin.clone() + ":" + id,
+            NFNodeData::Localhost => "core:localhost".into(),
+            NFNodeData::Router => "core:router".into(),
+            NFNodeData::InterfaceFilter(_) => "core:interface_filter".into(),
+            NFNodeData::CtStateFilter(_) => "core:ct_state_filter".into(),
+            NFNodeData::MarkFilter(_) => "core:mark_filter".into(),
+            NFNodeData::TimeFilter(_) => "core:time_filter".into(),
+            NFNodeData::SocketUserFilter(_) => "core:socket_user_filter".into(),
+            NFNodeData::SocketGroupFilter(_) => "core:socket_group_filter".into(),
+            NFNodeData::CgroupFilter(_) => "core:cgroup_filter".into(),
+            NFNodeData::SetMark(_) => "core:set_mark".into(),
+            NFNodeData::RouteVia(_) => "core:route_via".into(),
+            NFNodeData::RateLimit(_) => "core:rate_limit".into(),
+            NFNodeData::Quota(_) => "core:quota".into(),
+            NFNodeData::Log(_) => "core:log".into(),
+            NFNodeData::Counter(_) => "core:counter".into(),
+        }
+    }
+
+    /// Checks the parameters entered by the user, so mistakes are reported
+    /// on the node instead of at export time.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            NFNodeData::FileIpList(None) => Err(anyhow::anyhow!("IP List file is required")),
+            // relative paths are read from the graph directory, which is
+            // only known when compiling
+            NFNodeData::FileIpList(Some(path)) if path.is_absolute() && !path.is_file() => {
+                Err(anyhow::anyhow!("{} is not a file", path.to_string_lossy()))
+            }
+            NFNodeData::SourceAddressFilter(filter)
+            | NFNodeData::DestinationAddressFilter(filter) => params::addresses(filter).map(|_| ()),
+            NFNodeData::SourcePortFilter(filter) | NFNodeData::DestinationPortFilter(filter) => {
+                params::ports(filter).map(|_| ())
+            }
+            NFNodeData::ProtocolFilter(filter) => params::protocols(filter).map(|_| ()),
+            NFNodeData::TcpFlagsFilter(options) => options.validate(),
+            NFNodeData::TimeFilter(options) => options.validate(),
+            NFNodeData::IcmpTypeFilter(types) => params::icmp_types(&types.join(",")).map(|_| ()),
+            NFNodeData::InterfaceFilter(filter) => params::interfaces(filter).map(|_| ()),
+            NFNodeData::CtStateFilter(states) => params::ct_states(&states.join(",")).map(|_| ()),
+            NFNodeData::SourceNAT(target) | NFNodeData::DestinationNAT(target) => {
+                target.validate(true, false)
+            }
+            NFNodeData::LoadBalanceNAT(target) => target.validate(),
+            NFNodeData::Masquerade(target) => target.validate(false, false),
+            NFNodeData::Redirect(target) => target.validate(false, true),
+            NFNodeData::Reject(reason) => params::reject_reason(reason).map(|_| ()),
+            NFNodeData::MarkFilter(options) | NFNodeData::SetMark(options) => options.validate(),
+            NFNodeData::SocketUserFilter(filter) => params::users(filter).map(|_| ()),
+            NFNodeData::SocketGroupFilter(filter) => params::groups(filter).map(|_| ()),
+            NFNodeData::CgroupFilter(path) => params::cgroup(path).map(|_| ()),
+            NFNodeData::RouteVia(options) => options.validate(),
+            NFNodeData::RateLimit(options) => options.validate(),
+            NFNodeData::Quota(quota) => params::quota(quota).map(|_| ()),
+            NFNodeData::Log(options) => options.validate(),
+            NFNodeData::Counter(name) => params::object_name(name.trim()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for NFNodeData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NFNodeData::FileIpList(_) => write!(f, "IP File Filter"),
+            NFNodeData::SourceAddressFilter(_) => write!(f, "Source Address Filter"),
+            NFNodeData::DestinationAddressFilter(_) => write!(f, "Destination Address Filter"),
+            NFNodeData::SourcePortFilter(_) => write!(f, "Source Port Filter"),
+            NFNodeData::DestinationPortFilter(_) => write!(f, "Destination Port Filter"),
+            NFNodeData::ProtocolFilter(_) => write!(f, "Protocol Filter"),
+            NFNodeData::TcpFlagsFilter(_) => write!(f, "TCP Flags Filter"),
+            NFNodeData::IcmpTypeFilter(_) => write!(f, "ICMP Type Filter"),
+            NFNodeData::FamilySplitter => write!(f, "Family Splitter"),
+            NFNodeData::SourceNAT(_) => write!(f, "Source Address Translation"),
+            NFNodeData::DestinationNAT(_) => write!(f, "Destination Address Translation"),
+            NFNodeData::LoadBalanceNAT(_) => write!(f, "Load Balance NAT"),
+            NFNodeData::Masquerade(_) => write!(f, "Masquerade"),
+            NFNodeData::Redirect(_) => write!(f, "Redirect"),
+            NFNodeData::InterfaceFilter(_) => write!(f, "Interface Filter"),
+            NFNodeData::CtStateFilter(_) => write!(f, "Connection State Filter"),
+            NFNodeData::MarkFilter(_) => write!(f, "Mark Filter"),
+            NFNodeData::TimeFilter(_) => write!(f, "Time Filter"),
+            NFNodeData::SocketUserFilter(_) => write!(f, "Socket User Filter"),
+            NFNodeData::SocketGroupFilter(_) => write!(f, "Socket Group Filter"),
+            NFNodeData::CgroupFilter(_) => write!(f, "Cgroup Filter"),
+            NFNodeData::SetMark(_) => write!(f, "Set Mark"),
+            NFNodeData::RouteVia(_) => write!(f, "Route Via"),
+            NFNodeData::RateLimit(_) => write!(f, "Rate Limit"),
+            NFNodeData::Quota(_) => write!(f, "Quota"),
+            NFNodeData::Log(_) => write!(f, "Log"),
+            NFNodeData::Counter(_) => write!(f, "Counter"),
+            NFNodeData::Source => write!(f, "Incoming Source"),
+            NFNodeData::Localhost => write!(f, "Local Machine"),
+            NFNodeData::Router => write!(f, "Router"),
+            NFNodeData::Drop => write!(f, "Drop"),
+            NFNodeData::Reject(_) => write!(f, "Reject"),
+            NFNodeData::Accept => write!(f, "Accept"),
+            NFNodeData::Custom { .. } => Err(std::fmt::Error),
+        }
+    }
+}
@@ -13,7 +13,7 @@ impl egui_node_graph::NodeTemplateTrait for NFNodeData {
 
     type CategoryType = ();
 
-    fn node_finder_label(&self, user_state: &mut Self::UserState) -> Cow<str> {
+    fn node_finder_label(&self, user_state: &mut Self::UserState) -> Cow<'_, str> {
         if let NFNodeData::Custom { plugin, id, .. } = self {
             let name = user_state.plugins[plugin][id].display_name.clone();
             Cow::from(name)
@@ -38,8 +38,8 @@ impl egui_node_graph::NodeTemplateTrait for NFNodeData {
     ) {
         use super::data_type::{
             DataType,
-            NFDirection::{Either, Incoming, Outgoing},
-            NFFamily::{Inet, IPv4, IPv6},
+            NFDirection::{Either, Forwarded, Incoming, Outgoing},
+            NFFamily::{IPv4, IPv6, Inet},
         };
 
         if let Self::Custom { plugin, id, .. } = self {
@@ -54,11 +54,27 @@ impl egui_node_graph::NodeTemplateTrait for NFNodeData {
             | NFNodeData::SourcePortFilter(_)
             | NFNodeData::DestinationPortFilter(_)
             | NFNodeData::ProtocolFilter(_)
+            | NFNodeData::TcpFlagsFilter(_)
+            | NFNodeData::IcmpTypeFilter(_)
+            | NFNodeData::TimeFilter(_)
             | NFNodeData::FamilySplitter
             | NFNodeData::Drop
+            | NFNodeData::Reject(_)
+            | NFNodeData::Accept
             | NFNodeData::InterfaceFilter(_)
+            | NFNodeData::CtStateFilter(_)
+            | NFNodeData::MarkFilter(_)
+            | NFNodeData::SetMark(_)
+            | NFNodeData::RouteVia(_)
+            | NFNodeData::RateLimit(_)
+            | NFNodeData::Quota(_)
+            | NFNodeData::Log(_)
+            | NFNodeData::Counter(_)
             | NFNodeData::SourceNAT(_)
-            | NFNodeData::DestinationNAT(_) => {
+            | NFNodeData::DestinationNAT(_)
+            | NFNodeData::LoadBalanceNAT(_)
+            | NFNodeData::Masquerade(_)
+            | NFNodeData::Redirect(_) => {
                 graph.add_input_param(
                     node_id,
                     String::new(),
@@ -68,17 +84,19 @@ impl egui_node_graph::NodeTemplateTrait for NFNodeData {
                     true,
                 );
             }
-            NFNodeData::Accept => {
+            NFNodeData::SocketUserFilter(_)
+            | NFNodeData::SocketGroupFilter(_)
+            | NFNodeData::CgroupFilter(_) => {
                 graph.add_input_param(
                     node_id,
-                    "outgoing".into(),
+                    String::new(),
                     DataType::new(Inet, Outgoing),
                     super::ValueType,
                     egui_node_graph::InputParamKind::ConnectionOnly,
                     true,
                 );
             }
-            NFNodeData::Localhost => {
+            NFNodeData::Localhost | NFNodeData::Router => {
                 graph.add_input_param(
                     node_id,
                     "incoming".into(),
@@ -103,22 +121,54 @@ impl egui_node_graph::NodeTemplateTrait for NFNodeData {
             | NFNodeData::SourcePortFilter(_)
             | NFNodeData::DestinationPortFilter(_)
             | NFNodeData::InterfaceFilter(_)
+            | NFNodeData::CtStateFilter(_)
+            | NFNodeData::MarkFilter(_)
+            | NFNodeData::TcpFlagsFilter(_)
+            | NFNodeData::IcmpTypeFilter(_)
+            | NFNodeData::TimeFilter(_)
             | NFNodeData::ProtocolFilter(_) => {
                 graph.add_output_param(node_id, "match".into(), DataType::new(Inet, Either));
                 graph.add_output_param(node_id, "non-match".into(), DataType::new(Inet, Either));
             }
+            NFNodeData::RateLimit(_) => {
+                graph.add_output_param(node_id, "within limit".into(), DataType::new(Inet, Either));
+                graph.add_output_param(node_id, "over limit".into(), DataType::new(Inet, Either));
+            }
+            NFNodeData::Quota(_) => {
+                graph.add_output_param(node_id, "under quota".into(), DataType::new(Inet, Either));
+                graph.add_output_param(node_id, "over quota".into(), DataType::new(Inet, Either));
+            }
+            NFNodeData::SocketUserFilter(_)
+            | NFNodeData::SocketGroupFilter(_)
+            | NFNodeData::CgroupFilter(_) => {
+                graph.add_output_param(node_id, "match".into(), DataType::new(Inet, Outgoing));
+                graph.add_output_param(node_id, "non-match".into(), DataType::new(Inet, Outgoing));
+            }
             NFNodeData::Localhost => {
                 graph.add_output_param(node_id, "outgoing".into(), DataType::new(Inet, Outgoing));
             }
+            NFNodeData::Router => {
+                graph.add_output_param(node_id, "forwarded".into(), DataType::new(Inet, Forwarded));
+                graph.add_output_param(node_id, "local".into(), DataType::new(Inet, Incoming));
+            }
             NFNodeData::FamilySplitter => {
                 graph.add_output_param(node_id, "ipv4".into(), DataType::new(IPv4, Either));
                 graph.add_output_param(node_id, "ipv6".into(), DataType::new(IPv6, Either));
             }
-            NFNodeData::DestinationNAT(_) | NFNodeData::SourceNAT(_) => {
+            NFNodeData::DestinationNAT(_)
+            | NFNodeData::LoadBalanceNAT(_)
+            | NFNodeData::SourceNAT(_)
+            | NFNodeData::Masquerade(_)
+            | NFNodeData::Redirect(_)
+            | NFNodeData::Log(_)
+            | NFNodeData::Counter(_)
+            | NFNodeData::SetMark(_) => {
                 graph.add_output_param(node_id, "".into(), DataType::new(Inet, Either));
             }
             NFNodeData::Drop => {}
+            NFNodeData::Reject(_) => {}
             NFNodeData::Accept => {}
+            NFNodeData::RouteVia(_) => {}
             NFNodeData::Custom { .. } => {}
         }
     }
@@ -0,0 +1,60 @@
+use derive_more::Constructor;
+
+#[derive(Constructor, Clone)]
+pub struct NodeTemplateIter {
+    additional_kinds: Vec<super::NFNodeData>,
+}
+
+impl egui_node_graph::NodeTemplateIter for NodeTemplateIter {
+    type Item = super::NFNodeData;
+
+    fn all_kinds(&self) -> Vec<Self::Item> {
+        use super::NFNodeData::{
+            Accept, CgroupFilter, Counter, CtStateFilter, DestinationAddressFilter, DestinationNAT,
+            DestinationPortFilter, Drop, FamilySplitter, FileIpList, IcmpTypeFilter,
+            InterfaceFilter, LoadBalanceNAT, Log, MarkFilter, Masquerade, ProtocolFilter, Quota,
+            RateLimit, Redirect, Reject, RouteVia, Router, SetMark, SocketGroupFilter,
+            SocketUserFilter, SourceAddressFilter, SourceNAT, SourcePortFilter, TcpFlagsFilter,
+            TimeFilter,
+        };
+        use super::{
+            LimitOptions, LoadBalanceTarget, LogOptions, MarkOptions, NatTarget, RouteOptions,
+            TcpFlagsOptions, TimeOptions,
+        };
+        let core_kinds = vec![
+            InterfaceFilter(String::new()),
+            FileIpList(None),
+            SourceAddressFilter(String::new()),
+            DestinationAddressFilter(String::new()),
+            SourcePortFilter(String::new()),
+            DestinationPortFilter(String::new()),
+            ProtocolFilter(String::new()),
+            TcpFlagsFilter(TcpFlagsOptions::default()),
+            IcmpTypeFilter(vec![]),
+            CtStateFilter(vec![]),
+            MarkFilter(MarkOptions::default()),
+            TimeFilter(TimeOptions::default()),
+            SocketUserFilter(String::new()),
+            SocketGroupFilter(String::new()),
+            CgroupFilter(String::new()),
+            FamilySplitter,
+            RateLimit(LimitOptions::default()),
+            Quota(String::new()),
+            Log(LogOptions::default()),
+            Counter(String::new()),
+            SetMark(MarkOptions::default()),
+            Router,
+            SourceNAT(NatTarget::default()),
+            DestinationNAT(NatTarget::default()),
+            LoadBalanceNAT(LoadBalanceTarget::default()),
+            Masquerade(NatTarget::default()),
+            Redirect(NatTarget::default()),
+            Drop,
+            Reject("port-unreachable".into()),
+            Accept,
+            RouteVia(RouteOptions::default()),
+        ];
+
+        [core_kinds, self.additional_kinds.clone()].concat()
+    }
+}
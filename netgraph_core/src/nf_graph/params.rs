@@ -0,0 +1,582 @@
+use std::net::{IpAddr, SocketAddr};
+
+use ipnetwork::IpNetwork;
+
+/// Protocol names accepted by the Protocol Filter, as understood by nft.
+pub const PROTOCOLS: [&str; 11] = [
+    "tcp", "udp", "udplite", "sctp", "dccp", "icmp", "icmpv6", "igmp", "gre", "esp", "ah",
+];
+
+/// IANA numbers of [`PROTOCOLS`], in the same order.
+const PROTOCOL_NUMBERS: [u8; 11] = [6, 17, 136, 132, 33, 1, 58, 2, 47, 50, 51];
+
+/// Transport protocols carrying the port numbers matched by `th sport`/`th dport`.
+pub const PORT_PROTOCOLS: [&str; 5] = ["tcp", "udp", "udplite", "sctp", "dccp"];
+
+/// Connection tracking states matched by the Connection State Filter.
+pub const CT_STATES: [&str; 5] = ["new", "established", "related", "invalid", "untracked"];
+
+/// TCP flags, in the order of their bits in the header.
+pub const TCP_FLAGS: [&str; 8] = ["fin", "syn", "rst", "psh", "ack", "urg", "ecn", "cwr"];
+
+/// ICMP types of IPv4 matched by the ICMP Type Filter, as named by nft.
+pub const ICMP_TYPES: [&str; 11] = [
+    "echo-request",
+    "echo-reply",
+    "destination-unreachable",
+    "time-exceeded",
+    "parameter-problem",
+    "redirect",
+    "source-quench",
+    "router-advertisement",
+    "router-solicitation",
+    "timestamp-request",
+    "timestamp-reply",
+];
+
+/// ICMPv6 types matched by the ICMP Type Filter, as named by nft.
+pub const ICMPV6_TYPES: [&str; 13] = [
+    "echo-request",
+    "echo-reply",
+    "destination-unreachable",
+    "time-exceeded",
+    "parameter-problem",
+    "packet-too-big",
+    "nd-router-solicit",
+    "nd-router-advert",
+    "nd-neighbor-solicit",
+    "nd-neighbor-advert",
+    "nd-redirect",
+    "mld-listener-query",
+    "mld-listener-report",
+];
+
+/// Days of the week matched by the Time Filter, as named by nft.
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Syslog levels of the Log node, as named by nft.
+pub const LOG_LEVELS: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warn", "notice", "info", "debug",
+];
+
+/// Time units a rate can be given per.
+pub const RATE_PERIODS: [&str; 5] = ["second", "minute", "hour", "day", "week"];
+
+/// Units byte rates and quotas can be given in.
+pub const BYTE_UNITS: [&str; 3] = ["bytes", "kbytes", "mbytes"];
+
+/// Replies a Reject node can send: `tcp reset` or an ICMP code, as named by nft.
+pub const REJECT_REASONS: [&str; 10] = [
+    "port-unreachable",
+    "admin-prohibited",
+    "host-unreachable",
+    "no-route",
+    "tcp reset",
+    "net-unreachable",
+    "prot-unreachable",
+    "net-prohibited",
+    "host-prohibited",
+    "addr-unreachable",
+];
+
+fn list(filter: &str) -> impl Iterator<Item = &str> {
+    filter
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parses a comma separated list of addresses and CIDR networks.
+pub fn addresses(filter: &str) -> anyhow::Result<Vec<IpNetwork>> {
+    let addresses = list(filter)
+        .map(|item| {
+            item.parse::<IpNetwork>()
+                .map_err(|_| anyhow::anyhow!("`{item}` is not an IP address or network"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if addresses.is_empty() {
+        return Err(anyhow::anyhow!("Address is required"));
+    }
+    Ok(addresses)
+}
+
+/// Parses a comma separated list of ports and `first-last` port ranges.
+pub fn ports(filter: &str) -> anyhow::Result<Vec<(u16, u16)>> {
+    let port = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .map_err(|_| anyhow::anyhow!("`{port}` is not a port number"))
+    };
+    let ports = list(filter)
+        .map(|item| match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (port(first)?, port(last)?);
+                if first > last {
+                    return Err(anyhow::anyhow!("Port range `{item}` is reversed"));
+                }
+                Ok((first, last))
+            }
+            None => port(item).map(|port| (port, port)),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if ports.is_empty() {
+        return Err(anyhow::anyhow!("Port is required"));
+    }
+    Ok(ports)
+}
+
+/// Parses a comma separated list of protocol names or numbers.
+pub fn protocols(filter: &str) -> anyhow::Result<Vec<String>> {
+    let protocols = list(filter)
+        .map(|item| {
+            let item = item.to_lowercase();
+            if PROTOCOLS.contains(&item.as_str()) || item.parse::<u8>().is_ok() {
+                Ok(item)
+            } else {
+                Err(anyhow::anyhow!("`{item}` is not a known protocol"))
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if protocols.is_empty() {
+        return Err(anyhow::anyhow!("Protocol is required"));
+    }
+    Ok(protocols)
+}
+
+/// Returns the protocol number of a name or number accepted by [`protocols`].
+pub fn protocol_number(protocol: &str) -> Option<u8> {
+    PROTOCOLS
+        .iter()
+        .position(|name| *name == protocol)
+        .map(|index| PROTOCOL_NUMBERS[index])
+        .or_else(|| protocol.parse().ok())
+}
+
+/// Parses the address of a NAT node, a single address or a `first-last`
+/// range of the same family.
+pub fn nat_address(addr: &str) -> anyhow::Result<(IpAddr, Option<IpAddr>)> {
+    let parse = |addr: &str| {
+        addr.trim()
+            .parse::<IpAddr>()
+            .map_err(|_| anyhow::anyhow!("`{}` is not an address", addr.trim()))
+    };
+    if addr.trim().is_empty() {
+        return Err(anyhow::anyhow!("Address is required"));
+    }
+    let Some((first, last)) = addr.split_once('-') else {
+        return Ok((parse(addr)?, None));
+    };
+    let (first, last) = (parse(first)?, parse(last)?);
+    if first.is_ipv4() != last.is_ipv4() {
+        return Err(anyhow::anyhow!(
+            "IPv4 and IPv6 addresses cannot be mixed in one range"
+        ));
+    }
+    if first > last {
+        return Err(anyhow::anyhow!(
+            "Address range `{}` is reversed",
+            addr.trim()
+        ));
+    }
+    Ok((first, Some(last)))
+}
+
+/// Parses the backends of a Load Balance NAT node, a comma separated list
+/// of addresses followed by their weight, e.g. `10.0.0.2 3, 10.0.0.3 1`.
+pub fn backends(backends: &str) -> anyhow::Result<Vec<(IpAddr, u32)>> {
+    let backends = list(backends)
+        .map(|item| {
+            let (addr, weight) = item.split_once(' ').unwrap_or((item, "1"));
+            let addr = addr
+                .parse::<IpAddr>()
+                .map_err(|_| anyhow::anyhow!("`{addr}` is not an address"))?;
+            match weight.trim().parse::<u32>() {
+                Ok(weight) if (1..=1000).contains(&weight) => Ok((addr, weight)),
+                _ => Err(anyhow::anyhow!(
+                    "Weight of `{addr}` must be a number from 1 to 1000"
+                )),
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let Some((first, _)) = backends.first() else {
+        return Err(anyhow::anyhow!("At least one backend is required"));
+    };
+    if backends
+        .iter()
+        .any(|(addr, _)| addr.is_ipv4() != first.is_ipv4())
+    {
+        return Err(anyhow::anyhow!(
+            "IPv4 and IPv6 backends cannot be mixed in one node"
+        ));
+    }
+    Ok(backends)
+}
+
+/// Parses the port or `first-last` port range of a NAT node, empty when
+/// the port is kept.
+pub fn nat_port(port: &str) -> anyhow::Result<Option<(u16, u16)>> {
+    let port = port.trim();
+    if port.is_empty() {
+        return Ok(None);
+    }
+    let number = |number: &str| match number.trim().parse::<u16>() {
+        Ok(0) | Err(_) => Err(anyhow::anyhow!("`{number}` is not a port number")),
+        Ok(number) => Ok(number),
+    };
+    let (first, last) = match port.split_once('-') {
+        Some((first, last)) => (number(first)?, number(last)?),
+        None => (number(port)?, number(port)?),
+    };
+    if first > last {
+        return Err(anyhow::anyhow!("Port range `{port}` is reversed"));
+    }
+    Ok(Some((first, last)))
+}
+
+/// Parses an endpoint, e.g. a NAT target, written as `addr`, `addr:port`
+/// or `[addr6]:port`.
+pub fn endpoint(target: &str) -> anyhow::Result<(IpAddr, Option<u16>)> {
+    let target = target.trim();
+    if let Ok(addr) = target.parse::<SocketAddr>() {
+        return Ok((addr.ip(), Some(addr.port())));
+    }
+    target
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map(|addr| (addr, None))
+        .map_err(|_| anyhow::anyhow!("`{target}` is not an address or address:port"))
+}
+
+/// Parses a comma separated list of interface names. A trailing `*` matches
+/// every interface with the given prefix.
+pub fn interfaces(filter: &str) -> anyhow::Result<Vec<String>> {
+    let interfaces = list(filter)
+        .map(|item| {
+            let name = item.strip_suffix('*').unwrap_or(item);
+            if name.len() > 15 {
+                Err(anyhow::anyhow!("Interface name `{item}` is too long"))
+            } else if name.contains(|c: char| c.is_whitespace() || c == '/' || c == '*') {
+                Err(anyhow::anyhow!("`{item}` is not a valid interface name"))
+            } else {
+                Ok(item.to_string())
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if interfaces.is_empty() {
+        return Err(anyhow::anyhow!("Interface is required"));
+    }
+    Ok(interfaces)
+}
+
+/// Parses a comma separated list of user names or ids, resolving names
+/// with the local `/etc/passwd`.
+pub fn users(filter: &str) -> anyhow::Result<Vec<u32>> {
+    account_ids(filter, "/etc/passwd", "user")
+}
+
+/// Parses a comma separated list of group names or ids, resolving names
+/// with the local `/etc/group`.
+pub fn groups(filter: &str) -> anyhow::Result<Vec<u32>> {
+    account_ids(filter, "/etc/group", "group")
+}
+
+/// Looks names up in `database`, whose lines start with the name, a
+/// password field and the id, separated by colons.
+fn account_ids(filter: &str, database: &str, what: &str) -> anyhow::Result<Vec<u32>> {
+    let mut entries: Option<Vec<(String, u32)>> = None;
+    let mut ids = vec![];
+    for item in list(filter) {
+        if let Ok(id) = item.parse::<u32>() {
+            ids.push(id);
+            continue;
+        }
+        if entries.is_none() {
+            let contents = std::fs::read_to_string(database)
+                .map_err(|e| anyhow::anyhow!("Cannot read {database}: {e}"))?;
+            let parsed = contents
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.split(':');
+                    let name = fields.next()?;
+                    let id = fields.nth(1)?.parse().ok()?;
+                    Some((name.to_string(), id))
+                })
+                .collect();
+            entries = Some(parsed);
+        }
+        let id = entries
+            .iter()
+            .flatten()
+            .find(|(name, _)| name == item)
+            .map(|(_, id)| *id)
+            .ok_or(anyhow::anyhow!("No {what} `{item}` in {database}"))?;
+        ids.push(id);
+    }
+    if ids.is_empty() {
+        return Err(anyhow::anyhow!("A {what} is required"));
+    }
+    Ok(ids)
+}
+
+/// Parses a cgroup v2 path relative to the root of the hierarchy, e.g.
+/// `system.slice/nginx.service`, with its depth.
+pub fn cgroup(path: &str) -> anyhow::Result<(String, u32)> {
+    let path = path.trim().trim_matches('/');
+    if path.is_empty() {
+        return Err(anyhow::anyhow!("Cgroup path is required"));
+    }
+    if path.contains(|c: char| c.is_whitespace() || c == '"') || path.contains("//") {
+        return Err(anyhow::anyhow!("`{path}` is not a cgroup path"));
+    }
+    let level = path.split('/').count() as u32;
+    Ok((path.to_string(), level))
+}
+
+/// Parses a packet or connection mark, in decimal or as `0x` hex.
+pub fn mark(mark: &str) -> anyhow::Result<u32> {
+    let mark = mark.trim();
+    let parsed = match mark.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => mark.parse::<u32>(),
+    };
+    parsed.map_err(|_| anyhow::anyhow!("`{mark}` is not a mark"))
+}
+
+/// Parses a time of day as `HH:MM` into minutes since midnight.
+pub fn hour(hour: &str) -> anyhow::Result<u32> {
+    let hour = hour.trim();
+    let parsed = hour.split_once(':').and_then(|(h, m)| {
+        let (h, m) = (h.parse::<u32>().ok()?, two_digits(m)?);
+        (h < 24 && m < 60).then_some(h * 60 + m)
+    });
+    parsed.ok_or(anyhow::anyhow!("`{hour}` is not a time of day, e.g. 08:30"))
+}
+
+/// Parses a UTC date as `YYYY-MM-DD`, optionally followed by the time
+/// of day, into minutes since the start of 1970-01-01.
+pub fn date_time(date: &str) -> anyhow::Result<i64> {
+    let date = date.trim();
+    let invalid = || anyhow::anyhow!("`{date}` is not a date, e.g. 2024-12-24 or 2024-12-24 18:00");
+    let (day, time) = match date.split_once(' ') {
+        Some((day, time)) => (day, hour(time).map_err(|_| invalid())?),
+        None => (date, 0),
+    };
+    let mut fields = day.split('-');
+    let (Some(year), Some(month), Some(day), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return Err(invalid());
+    };
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let (month, day) = two_digits(month).zip(two_digits(day)).ok_or_else(invalid)?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return Err(invalid()),
+    };
+    if !(1..=month_days).contains(&day) {
+        return Err(invalid());
+    }
+    // days from the civil calendar, counting years from March so the leap
+    // day comes last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * i64::from((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    Ok(days * 24 * 60 + i64::from(time))
+}
+
+/// The index of the day in [`WEEKDAYS`] for minutes since 1970-01-01, a
+/// Thursday.
+pub fn weekday(minutes: i64) -> usize {
+    (minutes.div_euclid(24 * 60) + 3).rem_euclid(7) as usize
+}
+
+fn two_digits(number: &str) -> Option<u32> {
+    if number.len() == 2 && number.bytes().all(|b| b.is_ascii_digit()) {
+        number.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parses the number of a routing table. `default`, `main` and `local` are
+/// managed by the system, so they cannot be picked.
+pub fn route_table(table: &str) -> anyhow::Result<u32> {
+    let table = table.trim();
+    match table.parse::<u32>() {
+        Ok(253..=255) => Err(anyhow::anyhow!("Table {table} is reserved by the system")),
+        Ok(0) | Err(_) => Err(anyhow::anyhow!("`{table}` is not a routing table number")),
+        Ok(table) => Ok(table),
+    }
+}
+
+/// Parses a comma separated list of connection tracking states.
+pub fn ct_states(filter: &str) -> anyhow::Result<Vec<String>> {
+    let states = list(filter)
+        .map(|item| {
+            let item = item.to_lowercase();
+            if CT_STATES.contains(&item.as_str()) {
+                Ok(item)
+            } else {
+                Err(anyhow::anyhow!("`{item}` is not a connection state"))
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if states.is_empty() {
+        return Err(anyhow::anyhow!("Connection state is required"));
+    }
+    Ok(states)
+}
+
+/// Parses a comma separated list of TCP flags into the bits they stand
+/// for, which may be none.
+pub fn tcp_flags(filter: &str) -> anyhow::Result<u32> {
+    list(filter).try_fold(0, |bits, item| {
+        let item = item.to_lowercase();
+        let bit = TCP_FLAGS
+            .iter()
+            .position(|flag| *flag == item)
+            .ok_or(anyhow::anyhow!("`{item}` is not a TCP flag"))?;
+        Ok(bits | 1 << bit)
+    })
+}
+
+/// Parses a comma separated list of ICMP or ICMPv6 types.
+pub fn icmp_types(filter: &str) -> anyhow::Result<Vec<String>> {
+    let types = list(filter)
+        .map(|item| {
+            let item = item.to_lowercase();
+            if ICMP_TYPES.contains(&item.as_str()) || ICMPV6_TYPES.contains(&item.as_str()) {
+                Ok(item)
+            } else {
+                Err(anyhow::anyhow!("`{item}` is not an ICMP type"))
+            }
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if types.is_empty() {
+        return Err(anyhow::anyhow!("ICMP type is required"));
+    }
+    Ok(types)
+}
+
+/// Checks the reply of a Reject node.
+pub fn reject_reason(reason: &str) -> anyhow::Result<String> {
+    let reason = reason.trim().to_lowercase();
+    if reason.is_empty() {
+        return Err(anyhow::anyhow!("Reject reason is required"));
+    }
+    if !REJECT_REASONS.contains(&reason.as_str()) {
+        return Err(anyhow::anyhow!("`{reason}` is not a reject reason"));
+    }
+    Ok(reason)
+}
+
+/// A rate parsed by [`rate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rate {
+    pub count: u32,
+    /// One of [`BYTE_UNITS`], `None` for packets.
+    pub unit: Option<String>,
+    /// One of [`RATE_PERIODS`].
+    pub per: String,
+}
+
+/// Parses a rate written as `count/period` for packets or
+/// `count unit/period` for bytes, e.g. `10/minute` or `2 mbytes/second`.
+pub fn rate(rate: &str) -> anyhow::Result<Rate> {
+    let rate = rate.trim();
+    let invalid =
+        || anyhow::anyhow!("`{rate}` is not a rate like `10/minute` or `2 mbytes/second`");
+    let (amount, period) = rate.split_once('/').ok_or_else(invalid)?;
+    let (count, unit) = match amount.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [count] | [count, "packets"] => (*count, None),
+        [count, unit] if BYTE_UNITS.contains(unit) => (*count, Some(unit.to_string())),
+        _ => return Err(invalid()),
+    };
+    let count = count.parse::<u32>().map_err(|_| invalid())?;
+    let per = period.trim().to_lowercase();
+    if count == 0 || !RATE_PERIODS.contains(&per.as_str()) {
+        return Err(invalid());
+    }
+    Ok(Rate { count, unit, per })
+}
+
+/// Parses the burst of a limit, empty for none.
+pub fn burst(burst: &str) -> anyhow::Result<Option<u32>> {
+    let burst = burst.trim();
+    if burst.is_empty() {
+        return Ok(None);
+    }
+    burst
+        .parse::<u32>()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("`{burst}` is not a burst size"))
+}
+
+/// Parses an amount of data written as `count unit`, e.g. `500 mbytes`.
+pub fn quota(quota: &str) -> anyhow::Result<(u32, String)> {
+    let quota = quota.trim();
+    let invalid = || anyhow::anyhow!("`{quota}` is not an amount like `500 mbytes`");
+    match quota.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [count, unit] if BYTE_UNITS.contains(unit) => match count.parse::<u32>() {
+            Ok(count) if count > 0 => Ok((count, unit.to_string())),
+            _ => Err(invalid()),
+        },
+        _ => Err(invalid()),
+    }
+}
+
+/// Checks the prefix of a Log node, which nft limits to 127 characters.
+pub fn log_prefix(prefix: &str) -> anyhow::Result<()> {
+    if prefix.len() > 127 {
+        return Err(anyhow::anyhow!("Log prefix is longer than 127 characters"));
+    }
+    if prefix.contains('"') {
+        return Err(anyhow::anyhow!("Log prefix cannot contain `\"`"));
+    }
+    Ok(())
+}
+
+/// Parses the netlink group of a Log node, empty to log to syslog.
+pub fn log_group(group: &str) -> anyhow::Result<Option<u16>> {
+    let group = group.trim();
+    if group.is_empty() {
+        return Ok(None);
+    }
+    group
+        .parse::<u16>()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("`{group}` is not a log group"))
+}
+
+/// Checks the name of a named object such as a counter, empty for none.
+pub fn object_name(name: &str) -> anyhow::Result<()> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name.len() <= 63;
+    if name.is_empty() || valid {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "`{name}` is not a valid name, use letters, digits and `_`"
+        ))
+    }
+}
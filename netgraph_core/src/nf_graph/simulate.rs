@@ -0,0 +1,713 @@
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::path::Path;
+
+use nftables::types::NfChainPolicy;
+
+use egui_node_graph::{Graph, NodeId, OutputId};
+
+use super::{params, DataType, NFDirection, NFFamily, NFNodeData, ValueType};
+use crate::compile::{self, BaseChain, ChainSettings};
+use crate::ip_list::IpList;
+
+/// A hypothetical packet traced through the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Packet {
+    pub protocol: String,
+    pub saddr: IpAddr,
+    pub sport: Option<u16>,
+    pub daddr: IpAddr,
+    pub dport: Option<u16>,
+    pub iifname: String,
+    pub oifname: String,
+    /// Connection tracking state, e.g. `established`.
+    pub ct_state: String,
+    /// Packet and connection marks, 0 until a Set Mark node changes them.
+    pub mark: u32,
+    pub ct_mark: u32,
+    /// Bits of the set TCP flags, in the order of [`params::TCP_FLAGS`],
+    /// for TCP Flags Filters.
+    pub tcp_flags: Option<u32>,
+    /// ICMP or ICMPv6 type, for ICMP Type Filters.
+    pub icmp_type: Option<String>,
+    /// UTC time the packet is sent at, in minutes since 1970-01-01, for
+    /// Time Filters.
+    pub time: Option<i64>,
+}
+
+/// Packet fields as entered by the user.
+#[derive(Debug, Clone, Default)]
+pub struct PacketFields {
+    pub protocol: String,
+    pub source: String,
+    pub destination: String,
+    pub iifname: String,
+    /// Left empty for packets addressed to this machine, which the Router
+    /// sends through its `local` output.
+    pub oifname: String,
+    /// Left empty for the first packet of a connection.
+    pub ct_state: String,
+    /// Set TCP flags, only needed for TCP Flags Filters.
+    pub tcp_flags: String,
+    /// Only needed for ICMP Type Filters.
+    pub icmp_type: String,
+    /// Date and time of day in UTC, only needed for Time Filters.
+    pub time: String,
+}
+
+/// Where a node sends the packet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Hop {
+    Output(String),
+    Verdict(Verdict),
+    /// The packet leaves through none of the outputs of the node.
+    Stop(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+    /// Dropped with a reply to the sender, e.g. `tcp reset`.
+    Reject(String),
+    /// No path handled the packet, so the policy of the base chain applies.
+    Policy(BaseChain, NfChainPolicy),
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceStep {
+    pub node: NodeId,
+    pub label: String,
+    pub note: String,
+}
+
+/// The way a packet took through the graph.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    /// Connections taken, identified by their output.
+    pub connections: Vec<OutputId>,
+    pub rewrites: Vec<String>,
+    pub verdict: Verdict,
+    pub packet: Packet,
+}
+
+impl PacketFields {
+    pub fn packet(&self) -> anyhow::Result<Packet> {
+        let protocol = match params::protocols(&self.protocol)?.as_slice() {
+            [protocol] => protocol.clone(),
+            _ => return Err(anyhow::anyhow!("Enter a single protocol")),
+        };
+        let (saddr, sport) = params::endpoint(&self.source)?;
+        let (daddr, dport) = params::endpoint(&self.destination)?;
+        if saddr.is_ipv4() != daddr.is_ipv4() {
+            return Err(anyhow::anyhow!(
+                "Source and destination must be of the same family"
+            ));
+        }
+        let ct_state = match self.ct_state.trim() {
+            "" => "new".to_string(),
+            state => match params::ct_states(state)?.as_slice() {
+                [state] => state.clone(),
+                _ => return Err(anyhow::anyhow!("Enter a single connection state")),
+            },
+        };
+        let tcp_flags = match self.tcp_flags.trim() {
+            "" => None,
+            _ if protocol != "tcp" => {
+                return Err(anyhow::anyhow!("Only TCP packets have TCP flags"))
+            }
+            flags => Some(params::tcp_flags(flags)?),
+        };
+        let icmp_type = match self.icmp_type.trim() {
+            "" => None,
+            icmp_type => {
+                let types = match protocol.as_str() {
+                    "icmp" => params::ICMP_TYPES.as_slice(),
+                    "icmpv6" => params::ICMPV6_TYPES.as_slice(),
+                    _ => return Err(anyhow::anyhow!("Only ICMP packets have an ICMP type")),
+                };
+                let icmp_type = icmp_type.to_lowercase();
+                if !types.contains(&icmp_type.as_str()) {
+                    return Err(anyhow::anyhow!(
+                        "`{icmp_type}` is not a type of {protocol} packets"
+                    ));
+                }
+                Some(icmp_type)
+            }
+        };
+        let time = match self.time.trim() {
+            "" => None,
+            time => Some(params::date_time(time)?),
+        };
+        Ok(Packet {
+            protocol,
+            saddr,
+            sport,
+            daddr,
+            dport,
+            iifname: self.iifname.trim().to_string(),
+            oifname: self.oifname.trim().to_string(),
+            ct_state,
+            mark: 0,
+            ct_mark: 0,
+            tcp_flags,
+            icmp_type,
+            time,
+        })
+    }
+}
+
+impl Display for Packet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let endpoint = |addr: IpAddr, port: Option<u16>| match (addr, port) {
+            (IpAddr::V4(addr), Some(port)) => format!("{addr}:{port}"),
+            (IpAddr::V6(addr), Some(port)) => format!("[{addr}]:{port}"),
+            (addr, None) => addr.to_string(),
+        };
+        write!(
+            f,
+            "{} {} to {}",
+            self.protocol,
+            endpoint(self.saddr, self.sport),
+            endpoint(self.daddr, self.dport)
+        )?;
+        if self.mark != 0 {
+            write!(f, ", mark {:#x}", self.mark)?;
+        }
+        if self.ct_mark != 0 {
+            write!(f, ", ct mark {:#x}", self.ct_mark)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Accept => write!(f, "Accepted"),
+            Verdict::Drop => write!(f, "Dropped"),
+            Verdict::Reject(reason) => write!(f, "Rejected with {reason}"),
+            Verdict::Policy(base, policy) => {
+                write!(f, "Not handled, `{}` chain policy: {policy:?}", base.name())
+            }
+            Verdict::Error(err) => write!(f, "Cannot simulate: {err}"),
+        }
+    }
+}
+
+impl NFNodeData {
+    /// Applies the node to `packet` the way the compiled rules would, and
+    /// returns where the packet goes next.
+    /// Relative IP list paths are read from `graph_dir`.
+    pub fn simulate(
+        &self,
+        packet: &mut Packet,
+        direction: NFDirection,
+        graph_dir: Option<&Path>,
+    ) -> anyhow::Result<Hop> {
+        let rule = |matched: bool| Hop::Output(if matched { "match" } else { "non-match" }.into());
+        let hop = match self {
+            NFNodeData::Source => Hop::Output("incoming".into()),
+            NFNodeData::Localhost => Hop::Output("outgoing".into()),
+            // only forwarded packets have an outgoing interface before the
+            // output hook
+            NFNodeData::Router if packet.oifname.is_empty() => Hop::Output("local".into()),
+            NFNodeData::Router => Hop::Output("forwarded".into()),
+            NFNodeData::Accept => Hop::Verdict(Verdict::Accept),
+            NFNodeData::Drop => Hop::Verdict(Verdict::Drop),
+            NFNodeData::Reject(reason) => {
+                let reason = params::reject_reason(reason)?;
+                if reason == "tcp reset" && packet.protocol != "tcp" {
+                    Hop::Verdict(Verdict::Reject("port-unreachable".into()))
+                } else {
+                    Hop::Verdict(Verdict::Reject(reason))
+                }
+            }
+            NFNodeData::SourceAddressFilter(filter)
+            | NFNodeData::DestinationAddressFilter(filter) => {
+                let networks = params::addresses(filter)?;
+                let addr = if matches!(self, NFNodeData::SourceAddressFilter(_)) {
+                    packet.saddr
+                } else {
+                    packet.daddr
+                };
+                if networks.iter().any(|net| net.is_ipv4() != addr.is_ipv4()) {
+                    return Ok(Hop::Stop(
+                        "the filter is of the other address family".into(),
+                    ));
+                }
+                rule(networks.iter().any(|net| net.contains(addr)))
+            }
+            NFNodeData::FileIpList(path) => {
+                let path = path
+                    .as_ref()
+                    .ok_or(anyhow::anyhow!("IP List file is required"))?;
+                let ip_list = IpList::read(path, graph_dir)?;
+                if ip_list.family != packet.family() {
+                    return Ok(Hop::Stop("the list is of the other address family".into()));
+                }
+                rule(ip_list.contains(packet.saddr))
+            }
+            NFNodeData::SourcePortFilter(filter) | NFNodeData::DestinationPortFilter(filter) => {
+                let ports = params::ports(filter)?;
+                if !params::PORT_PROTOCOLS.contains(&packet.protocol.as_str()) {
+                    return Ok(Hop::Stop(format!(
+                        "{} does not carry ports",
+                        packet.protocol
+                    )));
+                }
+                let (port, name) = if matches!(self, NFNodeData::SourcePortFilter(_)) {
+                    (packet.sport, "source")
+                } else {
+                    (packet.dport, "destination")
+                };
+                let port = port.ok_or(anyhow::anyhow!("Packet has no {name} port"))?;
+                rule(
+                    ports
+                        .iter()
+                        .any(|(first, last)| (*first..=*last).contains(&port)),
+                )
+            }
+            NFNodeData::ProtocolFilter(filter) => {
+                let protocol = params::protocol_number(&packet.protocol);
+                rule(
+                    params::protocols(filter)?
+                        .iter()
+                        .any(|p| params::protocol_number(p) == protocol),
+                )
+            }
+            NFNodeData::TcpFlagsFilter(options) => {
+                let (flags, mask) = options.bits()?;
+                // packets without TCP flags do not match any
+                if packet.protocol != "tcp" {
+                    return Ok(rule(false));
+                }
+                let set = packet
+                    .tcp_flags
+                    .ok_or(anyhow::anyhow!("Enter the TCP flags of the packet"))?;
+                rule(set & mask == flags)
+            }
+            NFNodeData::IcmpTypeFilter(types) => {
+                let (protocol, field) = if packet.family() == NFFamily::IPv4 {
+                    ("icmp", "ICMP type")
+                } else {
+                    ("icmpv6", "ICMPv6 type")
+                };
+                if packet.protocol != protocol {
+                    return Ok(rule(false));
+                }
+                let icmp_type = packet
+                    .icmp_type
+                    .as_ref()
+                    .ok_or(anyhow::anyhow!("Enter the {field} of the packet"))?;
+                rule(types.contains(icmp_type))
+            }
+            NFNodeData::InterfaceFilter(filter) => {
+                let name = if direction == NFDirection::Incoming {
+                    &packet.iifname
+                } else {
+                    &packet.oifname
+                };
+                rule(params::interfaces(filter)?.iter().any(|pattern| {
+                    pattern
+                        .strip_suffix('*')
+                        .map_or(name == pattern, |prefix| name.starts_with(prefix))
+                }))
+            }
+            NFNodeData::CtStateFilter(states) => rule(states.contains(&packet.ct_state)),
+            NFNodeData::TimeFilter(options) => {
+                let time = packet
+                    .time
+                    .ok_or(anyhow::anyhow!("Enter the time the packet is sent at"))?;
+                rule(options.contains(time)?)
+            }
+            NFNodeData::SocketUserFilter(_)
+            | NFNodeData::SocketGroupFilter(_)
+            | NFNodeData::CgroupFilter(_) => {
+                return Err(anyhow::anyhow!(
+                    "The process sending the packet is not known, so its socket cannot be matched"
+                ))
+            }
+            NFNodeData::MarkFilter(options) => {
+                let mark = params::mark(&options.mark)?;
+                rule(*packet.mark_mut(options.conntrack) == mark)
+            }
+            NFNodeData::SetMark(options) => {
+                *packet.mark_mut(options.conntrack) = params::mark(&options.mark)?;
+                Hop::Output(String::new())
+            }
+            // the path ends with the mark, the verdict comes from the rules
+            // of other paths or the policy
+            NFNodeData::RouteVia(options) => {
+                if direction == NFDirection::Forwarded {
+                    return Err(anyhow::anyhow!(
+                        "Route Via must be placed before the Router"
+                    ));
+                }
+                packet.mark = params::mark(&options.mark)?;
+                Hop::Stop(format!("routed by table {}", options.table.trim()))
+            }
+            NFNodeData::FamilySplitter => Hop::Output(
+                if packet.saddr.is_ipv4() {
+                    "ipv4"
+                } else {
+                    "ipv6"
+                }
+                .into(),
+            ),
+            NFNodeData::SourceNAT(target) | NFNodeData::DestinationNAT(target) => {
+                // a range translates to its first address here
+                let (addr, _) = params::nat_address(&target.addr)?;
+                let port = nat_port(&target.port)?;
+                if addr.is_ipv4() != packet.saddr.is_ipv4() {
+                    return Ok(Hop::Stop("the NAT address is of the other family".into()));
+                }
+                if matches!(self, NFNodeData::SourceNAT(_)) {
+                    packet.saddr = addr;
+                    packet.sport = port.or(packet.sport);
+                } else if direction == NFDirection::Forwarded {
+                    return Err(anyhow::anyhow!(
+                        "Destination NAT must be placed before the Router"
+                    ));
+                } else {
+                    packet.daddr = addr;
+                    packet.dport = port.or(packet.dport);
+                }
+                Hop::Output(String::new())
+            }
+            // a single packet stays within any limit
+            NFNodeData::RateLimit(_) => Hop::Output("within limit".into()),
+            NFNodeData::Quota(_) => Hop::Output("under quota".into()),
+            // which backend a packet gets depends on the packets before it,
+            // so the first one stands in for all of them
+            NFNodeData::LoadBalanceNAT(target) => {
+                let (addr, _) = params::backends(&target.backend_list())?[0];
+                if addr.is_ipv4() != packet.saddr.is_ipv4() {
+                    return Ok(Hop::Stop("the backends are of the other family".into()));
+                }
+                if direction == NFDirection::Forwarded {
+                    return Err(anyhow::anyhow!(
+                        "Load Balance NAT must be placed before the Router"
+                    ));
+                }
+                packet.daddr = addr;
+                packet.dport = nat_port(&target.port)?.or(packet.dport);
+                Hop::Output(String::new())
+            }
+            NFNodeData::Log(_) | NFNodeData::Counter(_) => Hop::Output(String::new()),
+            // the address of the outgoing interface and the local address
+            // a packet is redirected to are not known, so only ports change
+            NFNodeData::Masquerade(target) => {
+                if direction == NFDirection::Incoming {
+                    return Err(anyhow::anyhow!(
+                        "Masquerade must be placed after Local Machine or the Router"
+                    ));
+                }
+                packet.sport = nat_port(&target.port)?.or(packet.sport);
+                Hop::Output(String::new())
+            }
+            NFNodeData::Redirect(target) => {
+                if direction == NFDirection::Forwarded {
+                    return Err(anyhow::anyhow!("Redirect must be placed before the Router"));
+                }
+                packet.dport = nat_port(&target.port)?.or(packet.dport);
+                Hop::Output(String::new())
+            }
+            NFNodeData::Custom { .. } => {
+                return Err(anyhow::anyhow!("Plugin nodes cannot be simulated"))
+            }
+        };
+        Ok(hop)
+    }
+}
+
+impl Packet {
+    fn mark_mut(&mut self, conntrack: bool) -> &mut u32 {
+        if conntrack {
+            &mut self.ct_mark
+        } else {
+            &mut self.mark
+        }
+    }
+
+    fn family(&self) -> NFFamily {
+        if self.saddr.is_ipv4() {
+            NFFamily::IPv4
+        } else {
+            NFFamily::IPv6
+        }
+    }
+}
+
+/// The port a NAT node translates to. A range translates to its first port,
+/// like it does for the first connection.
+fn nat_port(port: &str) -> anyhow::Result<Option<u16>> {
+    Ok(params::nat_port(port)?.map(|(first, _)| first))
+}
+
+/// Walks the graph from `source`, following the outputs chosen by each node
+/// until the packet gets a verdict.
+pub fn trace(
+    graph: &Graph<NFNodeData, DataType, ValueType>,
+    source: NodeId,
+    chains: &ChainSettings,
+    graph_dir: Option<&Path>,
+    mut packet: Packet,
+) -> Trace {
+    let mut steps = vec![];
+    let mut connections = vec![];
+    let mut rewrites = vec![];
+    let mut direction = NFDirection::Incoming;
+    let mut node_id = source;
+    let policy = |direction| {
+        let base = compile::filter_chain(direction);
+        Verdict::Policy(base, chains.get(base).policy)
+    };
+
+    // looping connections are broken in the editor, this is a safeguard
+    let verdict = loop {
+        if steps.len() > graph.nodes.len() * 2 {
+            break Verdict::Error("The graph contains a loop".into());
+        }
+        let node = &graph[node_id];
+        let before = packet.clone();
+        let hop = match node.user_data.simulate(&mut packet, direction, graph_dir) {
+            Ok(hop) => hop,
+            Err(err) => break Verdict::Error(format!("{}: {err}", node.label)),
+        };
+        if packet != before {
+            rewrites.push(format!("{}: {before} became {packet}", node.label));
+        }
+        let mut step = TraceStep {
+            node: node_id,
+            label: node.label.clone(),
+            note: String::new(),
+        };
+        let output_name = match hop {
+            Hop::Verdict(verdict) => {
+                steps.push(step);
+                break verdict;
+            }
+            Hop::Stop(reason) => {
+                step.note = format!("no output, {reason}");
+                steps.push(step);
+                break policy(direction);
+            }
+            Hop::Output(output_name) => output_name,
+        };
+        step.note.clone_from(&output_name);
+        steps.push(step);
+
+        let Ok(output_id) = node.get_output(&output_name) else {
+            // Routers of graphs saved before they had a `local` output
+            if output_name == "local" {
+                break policy(NFDirection::Incoming);
+            }
+            break Verdict::Error(format!("{} has no output `{output_name}`", node.label));
+        };
+        let output_direction = graph.outputs[output_id].typ.direction;
+        if output_direction != NFDirection::Either {
+            direction = output_direction;
+        }
+        let Some(input_id) = graph.connections.get(output_id) else {
+            break policy(direction);
+        };
+        connections.push(output_id);
+        node_id = graph.inputs[*input_id].node;
+    };
+
+    Trace {
+        steps,
+        connections,
+        rewrites,
+        verdict,
+        packet,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeTemplateTrait;
+
+    use super::super::{NFGraphState, NatTarget, TcpFlagsOptions, TimeOptions};
+    use super::*;
+
+    #[test]
+    fn test_trace_through_localhost() {
+        let mut graph = Graph::new();
+        let mut user_state = NFGraphState::default();
+        let mut add_node = |graph: &mut Graph<_, _, _>, node: NFNodeData| {
+            let label = node.node_graph_label(&mut user_state);
+            graph.add_node(label, node.clone(), |graph, node_id| {
+                node.build_node(graph, &mut user_state, node_id);
+            })
+        };
+        let source = add_node(&mut graph, NFNodeData::Source);
+        let filter = add_node(&mut graph, NFNodeData::DestinationPortFilter("22".into()));
+        let localhost = add_node(&mut graph, NFNodeData::Localhost);
+        let nat = add_node(
+            &mut graph,
+            NFNodeData::SourceNAT(NatTarget::from_endpoint("192.0.2.1")),
+        );
+        let mut connect = |from: NodeId, output: &str, to: NodeId| {
+            let output = graph[from].get_output(output).unwrap();
+            let input = graph[to].input_ids().next().unwrap();
+            graph.add_connection(output, input);
+        };
+        connect(source, "incoming", filter);
+        connect(filter, "match", localhost);
+        connect(localhost, "outgoing", nat);
+
+        let fields = PacketFields {
+            protocol: "tcp".into(),
+            source: "10.0.0.5:5555".into(),
+            destination: "192.168.1.10:22".into(),
+            iifname: "eth0".into(),
+            ..Default::default()
+        };
+        let traced = trace(
+            &graph,
+            source,
+            &ChainSettings::default(),
+            None,
+            fields.packet().unwrap(),
+        );
+        let notes: Vec<_> = traced.steps.iter().map(|step| step.note.as_str()).collect();
+        assert_eq!(notes, ["incoming", "match", "outgoing", ""]);
+        assert_eq!(traced.connections.len(), 3);
+        assert_eq!(traced.packet.saddr, "192.0.2.1".parse::<IpAddr>().unwrap());
+        assert_eq!(
+            traced.verdict,
+            Verdict::Policy(BaseChain::FilterOutput, NfChainPolicy::Accept)
+        );
+
+        let fields = PacketFields {
+            destination: "192.168.1.10:80".into(),
+            ..fields
+        };
+        let traced = trace(
+            &graph,
+            source,
+            &ChainSettings::default(),
+            None,
+            fields.packet().unwrap(),
+        );
+        assert_eq!(
+            traced.verdict,
+            Verdict::Policy(BaseChain::FilterInput, NfChainPolicy::Drop)
+        );
+    }
+
+    #[test]
+    fn test_nat_port_range() {
+        let target = NatTarget {
+            addr: "10.0.0.2".into(),
+            port: "8000-8010".into(),
+            ..Default::default()
+        };
+        assert!(target.validate(true, true).is_ok());
+        for port in ["8010-8000", "0-10", "8000-"] {
+            let reversed = NatTarget {
+                port: port.into(),
+                ..target.clone()
+            };
+            assert!(reversed.validate(true, true).is_err(), "{port}");
+        }
+
+        let fields = PacketFields {
+            protocol: "tcp".into(),
+            source: "198.51.100.7:5555".into(),
+            destination: "192.0.2.1:80".into(),
+            ..Default::default()
+        };
+        let mut packet = fields.packet().unwrap();
+        let hop =
+            NFNodeData::DestinationNAT(target).simulate(&mut packet, NFDirection::Incoming, None);
+        assert_eq!(hop.unwrap(), Hop::Output(String::new()));
+        // a range translates to its first port
+        assert_eq!(packet.dport, Some(8000));
+    }
+
+    #[test]
+    fn test_time_filter_across_midnight() {
+        let filter = NFNodeData::TimeFilter(TimeOptions {
+            hour_from: "22:00".into(),
+            hour_until: "06:00".into(),
+            days: vec!["Friday".into()],
+            ..Default::default()
+        });
+        let hop = |time: &str| {
+            let fields = PacketFields {
+                protocol: "tcp".into(),
+                source: "10.0.0.5:5555".into(),
+                destination: "192.168.1.10:22".into(),
+                time: time.into(),
+                ..Default::default()
+            };
+            filter.simulate(&mut fields.packet().unwrap(), NFDirection::Incoming, None)
+        };
+        let output = |name: &str| Hop::Output(name.into());
+        // 2024-12-27 is a Friday
+        assert_eq!(hop("2024-12-27 23:15").unwrap(), output("match"));
+        assert_eq!(hop("2024-12-27 01:30").unwrap(), output("match"));
+        assert_eq!(hop("2024-12-28 01:30").unwrap(), output("non-match"));
+        assert_eq!(hop("2024-12-27 12:00").unwrap(), output("non-match"));
+        assert!(hop("").is_err());
+        let NFNodeData::TimeFilter(options) = &filter else {
+            unreachable!()
+        };
+        assert!(options.warning().is_some());
+    }
+
+    #[test]
+    fn test_tcp_flags_and_icmp_type() {
+        let syn_only = NFNodeData::TcpFlagsFilter(TcpFlagsOptions::preset(
+            &["syn"],
+            &["fin", "syn", "rst", "ack"],
+        ));
+        let echo = NFNodeData::IcmpTypeFilter(vec!["echo-request".into()]);
+        let hop = |filter: &NFNodeData, protocol: &str, tcp_flags: &str, icmp_type: &str| {
+            let fields = PacketFields {
+                protocol: protocol.into(),
+                source: "2001:db8::5".into(),
+                destination: "2001:db8::1".into(),
+                tcp_flags: tcp_flags.into(),
+                icmp_type: icmp_type.into(),
+                ..Default::default()
+            };
+            filter.simulate(&mut fields.packet()?, NFDirection::Incoming, None)
+        };
+        let output = |name: &str| Hop::Output(name.into());
+        // unexamined flags may have any value
+        assert_eq!(
+            hop(&syn_only, "tcp", "syn,ecn", "").unwrap(),
+            output("match")
+        );
+        assert_eq!(
+            hop(&syn_only, "tcp", "syn,ack", "").unwrap(),
+            output("non-match")
+        );
+        assert!(hop(&syn_only, "tcp", "", "").is_err());
+        assert!(hop(&syn_only, "udp", "syn", "").is_err());
+        assert_eq!(hop(&syn_only, "udp", "", "").unwrap(), output("non-match"));
+
+        assert_eq!(
+            hop(&echo, "icmpv6", "", "echo-request").unwrap(),
+            output("match")
+        );
+        assert_eq!(
+            hop(&echo, "icmpv6", "", "packet-too-big").unwrap(),
+            output("non-match")
+        );
+        // the type is checked against the family of the packet
+        assert!(hop(&echo, "icmpv6", "", "redirect").is_err());
+        assert!(hop(&echo, "icmpv6", "", "").is_err());
+        assert_eq!(
+            hop(&echo, "icmp", "", "echo-request").unwrap(),
+            output("non-match")
+        );
+        assert_eq!(hop(&echo, "udp", "", "").unwrap(), output("non-match"));
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the TCP Flags Filter, which matches packets whose `mask`
+/// flags are exactly the ones in `flags`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TcpFlagsOptions {
+    /// Flags that have to be set, out of the examined ones.
+    pub flags: Vec<String>,
+    /// Flags examined, the others may have any value.
+    pub mask: Vec<String>,
+}
+
+impl TcpFlagsOptions {
+    /// Common combinations offered in the side panel.
+    pub const PRESETS: [(
+        &'static str,
+        &'static [&'static str],
+        &'static [&'static str],
+    ); 3] = [
+        ("SYN only", &["syn"], &["fin", "syn", "rst", "ack"]),
+        (
+            "Null scan",
+            &[],
+            &["fin", "syn", "rst", "psh", "ack", "urg"],
+        ),
+        (
+            "Xmas scan",
+            &["fin", "psh", "urg"],
+            &["fin", "syn", "rst", "psh", "ack", "urg"],
+        ),
+    ];
+
+    pub fn preset(flags: &[&str], mask: &[&str]) -> Self {
+        Self {
+            flags: flags.iter().map(|flag| (*flag).into()).collect(),
+            mask: mask.iter().map(|flag| (*flag).into()).collect(),
+        }
+    }
+
+    /// The bits of the set and of the examined flags.
+    pub fn bits(&self) -> anyhow::Result<(u32, u32)> {
+        let flags = params::tcp_flags(&self.flags.join(","))?;
+        let mask = params::tcp_flags(&self.mask.join(","))?;
+        if mask == 0 {
+            return Err(anyhow::anyhow!("Select the TCP flags to examine"));
+        }
+        if flags & !mask != 0 {
+            return Err(anyhow::anyhow!("Set TCP flags must also be examined"));
+        }
+        Ok((flags, mask))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.bits().map(|_| ())
+    }
+}
+
+impl Display for TcpFlagsOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flags = if self.flags.is_empty() {
+            "none".to_string()
+        } else {
+            self.flags.join(", ")
+        };
+        write!(f, "{flags} of {}", self.mask.join(", "))
+    }
+}
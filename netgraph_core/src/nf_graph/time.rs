@@ -0,0 +1,144 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::params;
+
+/// Parameters of the Time Filter, in UTC like the `meta hour` and
+/// `meta time` of the kernel. Empty fields match any time, the others all
+/// have to match.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeOptions {
+    /// Start of the daily hours as `HH:MM`.
+    pub hour_from: String,
+    /// End of the daily hours, excluded. Earlier than the start for hours
+    /// crossing midnight.
+    pub hour_until: String,
+    /// Names from [`params::WEEKDAYS`].
+    pub days: Vec<String>,
+    /// Start of the date range as `YYYY-MM-DD` with an optional `HH:MM`.
+    pub date_from: String,
+    /// End of the date range, excluded.
+    pub date_until: String,
+}
+
+/// Parsed [`TimeOptions`], in minutes.
+pub(crate) struct Window {
+    pub hours: Option<(u32, u32)>,
+    /// Indices in [`params::WEEKDAYS`].
+    pub days: Vec<usize>,
+    pub from: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl TimeOptions {
+    pub(crate) fn window(&self) -> anyhow::Result<Window> {
+        let hours = match (self.hour_from.trim(), self.hour_until.trim()) {
+            ("", "") => None,
+            ("", _) | (_, "") => {
+                return Err(anyhow::anyhow!("Both ends of the hours are required"))
+            }
+            (from, until) => Some((params::hour(from)?, params::hour(until)?)),
+        };
+        if matches!(hours, Some((from, until)) if from == until) {
+            return Err(anyhow::anyhow!("The hours must not start and end at once"));
+        }
+        let days = self
+            .days
+            .iter()
+            .map(|day| {
+                params::WEEKDAYS
+                    .iter()
+                    .position(|weekday| weekday.eq_ignore_ascii_case(day))
+                    .ok_or(anyhow::anyhow!("`{day}` is not a day of the week"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let date = |date: &str| match date.trim() {
+            "" => Ok(None),
+            date => params::date_time(date).map(Some),
+        };
+        let (from, until) = (date(&self.date_from)?, date(&self.date_until)?);
+        if let (Some(from), Some(until)) = (from, until) {
+            if from >= until {
+                return Err(anyhow::anyhow!("The date range ends before it starts"));
+            }
+        }
+        if hours.is_none() && days.is_empty() && from.is_none() && until.is_none() {
+            return Err(anyhow::anyhow!("Hours, days or dates are required"));
+        }
+        Ok(Window {
+            hours,
+            days,
+            from,
+            until,
+        })
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.window().map(|_| ())
+    }
+
+    /// Whether the filter matches at `time`, in minutes since 1970-01-01.
+    pub fn contains(&self, time: i64) -> anyhow::Result<bool> {
+        let window = self.window()?;
+        let minute = time.rem_euclid(24 * 60) as u32;
+        let in_hours = match window.hours {
+            None => true,
+            Some((from, until)) if from < until => (from..until).contains(&minute),
+            Some((from, until)) => minute >= from || minute < until,
+        };
+        // like nft, the day is the one of the packet, also past midnight
+        let on_day = window.days.is_empty() || window.days.contains(&params::weekday(time));
+        let in_dates = window.from.is_none_or(|from| time >= from)
+            && window.until.is_none_or(|until| time < until);
+        Ok(in_hours && on_day && in_dates)
+    }
+
+    /// Explains how hours crossing midnight match, which is easily
+    /// misread together with days.
+    pub fn warning(&self) -> Option<String> {
+        let window = self.window().ok()?;
+        let (from, until) = window.hours?;
+        if from < until {
+            return None;
+        }
+        let (from, until) = (self.hour_from.trim(), self.hour_until.trim());
+        Some(if window.days.is_empty() {
+            format!("The hours cross midnight, so they match from {from} until midnight and from midnight until {until}")
+        } else {
+            format!(
+                "The hours cross midnight, so until {until} they match on the selected days, \
+                 not on the days after them"
+            )
+        })
+    }
+}
+
+impl Display for TimeOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if !self.hour_from.trim().is_empty() {
+            parts.push(format!(
+                "{}-{}",
+                self.hour_from.trim(),
+                self.hour_until.trim()
+            ));
+        }
+        if !self.days.is_empty() {
+            let days: Vec<String> = self
+                .days
+                .iter()
+                .map(|day| day.chars().take(3).collect())
+                .collect();
+            parts.push(days.join(" "));
+        }
+        match (self.date_from.trim(), self.date_until.trim()) {
+            ("", "") => {}
+            (from, "") => parts.push(format!("from {from}")),
+            ("", until) => parts.push(format!("until {until}")),
+            (from, until) => parts.push(format!("{from} to {until}")),
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
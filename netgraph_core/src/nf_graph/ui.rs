@@ -0,0 +1,245 @@
+//! How the graph types are drawn in the editor, built with the `ui` feature
+//! only so the compiler and the command line do without egui.
+
+use std::borrow::Cow;
+
+use eframe::egui::{self, Button, Color32, RichText, Widget};
+
+use egui_node_graph::{DataTypeTrait, Graph, NodeId, NodeResponse};
+
+use super::{DataType, NFDirection, NFFamily, NFNodeData, ValueType};
+
+impl DataTypeTrait<super::NFGraphState> for DataType {
+    fn data_type_color(&self, _user_state: &mut super::NFGraphState) -> Color32 {
+        match self.direction {
+            NFDirection::Either => Color32::LIGHT_GRAY,
+            NFDirection::Incoming => Color32::LIGHT_RED,
+            NFDirection::Outgoing => Color32::LIGHT_BLUE,
+            NFDirection::Forwarded => Color32::LIGHT_GREEN,
+        }
+    }
+
+    fn name(&self) -> Cow<'_, str> {
+        let family = match self.family {
+            NFFamily::Inet => "inet",
+            NFFamily::IPv4 => "ipv4",
+            NFFamily::IPv6 => "ipv6",
+        };
+        let direction = match self.direction {
+            NFDirection::Either => "",
+            NFDirection::Incoming => "Incoming ",
+            NFDirection::Outgoing => "Outgoing ",
+            NFDirection::Forwarded => "Forwarded ",
+        };
+        Cow::from(direction.to_owned() + family)
+    }
+}
+
+impl egui_node_graph::WidgetValueTrait for ValueType {
+    type Response = super::NodeResponse;
+
+    type UserState = super::NFGraphState;
+
+    type NodeData = super::NFNodeData;
+
+    fn value_widget(
+        &mut self,
+        param_name: &str,
+        node_id: egui_node_graph::NodeId,
+        ui: &mut eframe::egui::Ui,
+        user_state: &mut Self::UserState,
+        node_data: &Self::NodeData,
+    ) -> Vec<Self::Response> {
+        self.value_widget_connected(param_name, node_id, ui, user_state, node_data)
+    }
+}
+
+impl egui_node_graph::NodeDataTrait for NFNodeData {
+    type Response = super::NodeResponse;
+
+    type UserState = super::NFGraphState;
+
+    type DataType = super::DataType;
+
+    type ValueType = super::ValueType;
+
+    fn bottom_ui(
+        &self,
+        ui: &mut egui::Ui,
+        node_id: egui_node_graph::NodeId,
+        _graph: &egui_node_graph::Graph<Self, Self::DataType, Self::ValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Vec<NodeResponse<Self::Response, Self>> {
+        use super::response::NodeResponse::SelectNode;
+        let mut responses = vec![];
+
+        ui.separator();
+        match self {
+            NFNodeData::Source
+            | NFNodeData::Drop
+            | NFNodeData::Accept
+            | NFNodeData::FamilySplitter
+            | NFNodeData::Router
+            | NFNodeData::Localhost => return responses,
+
+            NFNodeData::FileIpList(file) => {
+                ui.label("Matching list file");
+                if let Some(file) = file {
+                    ui.label(file.to_string_lossy());
+                }
+            }
+            NFNodeData::SourceAddressFilter(addr) => {
+                ui.label("Matching Source Address");
+                ui.label(addr);
+            }
+            NFNodeData::DestinationAddressFilter(addr) => {
+                ui.label("Matching Destination Address");
+                ui.label(addr);
+            }
+            NFNodeData::SourcePortFilter(ports) => {
+                ui.label("Matching Source Port");
+                ui.label(ports);
+            }
+            NFNodeData::DestinationPortFilter(ports) => {
+                ui.label("Matching Destination Port");
+                ui.label(ports);
+            }
+            NFNodeData::ProtocolFilter(protocols) => {
+                ui.label("Matching Protocol");
+                ui.label(protocols);
+            }
+            NFNodeData::TcpFlagsFilter(options) => {
+                ui.label("Matching TCP Flags");
+                ui.label(options.to_string());
+            }
+            NFNodeData::IcmpTypeFilter(types) => {
+                ui.label("Matching ICMP Type");
+                ui.label(types.join(", "));
+            }
+            NFNodeData::InterfaceFilter(interface) => {
+                ui.label("Matching Interface");
+                ui.label(interface);
+            }
+            NFNodeData::CtStateFilter(states) => {
+                ui.label("Matching Connection State");
+                ui.label(states.join(", "));
+            }
+            NFNodeData::MarkFilter(options) => {
+                ui.label("Matching Mark");
+                ui.label(options.to_string());
+            }
+            NFNodeData::TimeFilter(options) => {
+                ui.label("Matching Time");
+                ui.label(options.to_string());
+                if let Some(warning) = options.warning() {
+                    ui.colored_label(ui.visuals().warn_fg_color, warning);
+                }
+            }
+            NFNodeData::SocketUserFilter(users) => {
+                ui.label("Matching Socket User");
+                ui.label(users);
+            }
+            NFNodeData::SocketGroupFilter(groups) => {
+                ui.label("Matching Socket Group");
+                ui.label(groups);
+            }
+            NFNodeData::CgroupFilter(path) => {
+                ui.label("Matching Cgroup");
+                ui.label(path);
+            }
+            NFNodeData::SetMark(options) => {
+                ui.label("Setting");
+                ui.label(options.to_string());
+            }
+            NFNodeData::RouteVia(options) => {
+                ui.label("Routing by");
+                ui.label(options.to_string());
+            }
+            NFNodeData::RateLimit(options) => {
+                ui.label("Limiting to");
+                ui.label(options.to_string());
+            }
+            NFNodeData::Quota(quota) => {
+                ui.label("Quota");
+                ui.label(quota);
+            }
+            NFNodeData::Log(options) => {
+                ui.label("Logging");
+                ui.label(options.to_string());
+            }
+            NFNodeData::Counter(name) => {
+                ui.label("Counting");
+                ui.label(if name.trim().is_empty() {
+                    "in the rule"
+                } else {
+                    name.trim()
+                });
+            }
+            NFNodeData::SourceNAT(target) => {
+                ui.label("Send from");
+                ui.label(target.to_string());
+            }
+            NFNodeData::DestinationNAT(target) => {
+                ui.label("Send to");
+                ui.label(target.to_string());
+            }
+            NFNodeData::LoadBalanceNAT(target) => {
+                ui.label("Send to one of");
+                ui.label(target.to_string());
+            }
+            NFNodeData::Masquerade(target) => {
+                ui.label("Send from the outgoing interface");
+                ui.label(target.to_string());
+            }
+            NFNodeData::Redirect(target) => {
+                ui.label("Send to local port");
+                ui.label(target.to_string());
+            }
+            NFNodeData::Reject(reason) => {
+                ui.label("Reply with");
+                ui.label(reason);
+            }
+            NFNodeData::Custom { plugin, id, data } => {
+                ui.label(format!("{}", user_state.plugins[plugin][id]));
+                for (id, param) in &user_state.plugins[plugin][id].params {
+                    let val = data.get(id).cloned().unwrap_or(String::new());
+                    ui.label(format!("{param}: {val}"));
+                    ui.separator();
+                }
+            }
+        }
+
+        let is_active = user_state.active_node.is_some_and(|id| id == node_id);
+        if is_active {
+            Button::new(RichText::new("Edit").color(egui::Color32::BLACK))
+                .fill(egui::Color32::GOLD)
+                .ui(ui);
+        } else if ui.button("Edit").clicked() {
+            responses.push(NodeResponse::User(SelectNode(node_id)));
+        }
+        responses
+    }
+
+    fn titlebar_color(
+        &self,
+        _ui: &egui::Ui,
+        node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        user_state: &mut Self::UserState,
+    ) -> Option<egui::Color32> {
+        user_state
+            .validation
+            .error(node_id, self)
+            .is_some()
+            .then_some(egui::Color32::from_rgb(200, 60, 60))
+    }
+
+    fn can_delete(
+        &self,
+        _node_id: NodeId,
+        _graph: &Graph<Self, Self::DataType, Self::ValueType>,
+        _user_state: &mut Self::UserState,
+    ) -> bool {
+        !matches!(self, NFNodeData::Source | NFNodeData::Localhost)
+    }
+}
@@ -3,7 +3,7 @@ use std::fmt::Display;
 
 use serde::{Deserialize, Serialize};
 
-use super::nf_graph::{NFDirection, NFFamily};
+use crate::nf_graph::{NFDirection, NFFamily};
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct NFInput {
@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use serde_json::{to_value, Map};
+
+use egui_node_graph::NodeId;
+
+use crate::compile::ChainSettings;
+use crate::nf_graph::NFGraphState;
+use crate::NFEditorState;
+
+/// A graph directory: `graph.json` and the `plugins` its extensions were
+/// imported into.
+pub struct SavedGraph {
+    pub source_node: NodeId,
+    pub editor_state: NFEditorState,
+    /// Plugins and chain settings of the graph.
+    pub user_state: NFGraphState,
+}
+
+impl SavedGraph {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(dir.join("graph.json"))
+            .or(Err(anyhow::anyhow!("Cannot read graph file")))?;
+        let map: Map<_, _> = serde_json::from_str(&json)
+            .or(Err(anyhow::anyhow!("Graph file is not a JSON object")))?;
+        let field = |name: &str| {
+            map.get(name)
+                .cloned()
+                .ok_or(anyhow::anyhow!("incorrect file format"))
+        };
+        let source_node = serde_json::from_value(field("source_node")?)?;
+        let editor_state = serde_json::from_value(field("editor_state")?)?;
+        let plugins = serde_json::from_value(field("plugins")?)?;
+        let chains = match map.get("chains") {
+            Some(chains) => serde_json::from_value(chains.clone())?,
+            None => ChainSettings::default(),
+        };
+        Ok(Self {
+            source_node,
+            editor_state,
+            user_state: NFGraphState {
+                plugins,
+                chains,
+                ..Default::default()
+            },
+        })
+    }
+
+    pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+        save(dir, self.source_node, &self.editor_state, &self.user_state)
+    }
+}
+
+/// Writes a graph the way [`SavedGraph::save`] does, without taking
+/// ownership of the editor state.
+pub fn save(
+    dir: &Path,
+    source_node: NodeId,
+    editor_state: &NFEditorState,
+    user_state: &NFGraphState,
+) -> anyhow::Result<()> {
+    let source_node =
+        to_value(source_node).or(Err(anyhow::anyhow!("source node is not serializable")))?;
+    let editor_state =
+        to_value(editor_state).or(Err(anyhow::anyhow!("editor state is not serializable")))?;
+    let plugins =
+        to_value(&user_state.plugins).or(Err(anyhow::anyhow!("plugins is not serializable")))?;
+    let chains = to_value(&user_state.chains)
+        .or(Err(anyhow::anyhow!("chain settings are not serializable")))?;
+
+    let mut map = Map::new();
+    map.insert("source_node".to_string(), source_node);
+    map.insert("editor_state".to_string(), editor_state);
+    map.insert("plugins".to_string(), plugins);
+    map.insert("chains".to_string(), chains);
+    let json = serde_json::to_string(&map).unwrap();
+
+    std::fs::write(dir.join("graph.json"), json)
+        .or(Err(anyhow::anyhow!("Cannot write graph file")))?;
+    std::fs::create_dir_all(dir.join("plugins"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeTemplateTrait;
+    use nftables::types::NfChainPolicy;
+
+    use super::*;
+    use crate::compile::BaseChain;
+    use crate::nf_graph::NFNodeData;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("netgraph-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut editor_state = NFEditorState::default();
+        let mut user_state = NFGraphState::default();
+        let node = NFNodeData::Source;
+        let source_node = editor_state.graph.add_node(
+            node.node_graph_label(&mut user_state),
+            node.user_data(&mut user_state),
+            |graph, node_id| node.build_node(graph, &mut user_state, node_id),
+        );
+        let mut input = user_state.chains.get(BaseChain::FilterInput);
+        input.policy = NfChainPolicy::Accept;
+        user_state
+            .chains
+            .chains
+            .insert(BaseChain::FilterInput, input);
+        save(&dir, source_node, &editor_state, &user_state).unwrap();
+
+        let saved = SavedGraph::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saved.source_node, source_node);
+        assert!(saved.editor_state.graph[source_node].user_data.is_source());
+        assert_eq!(
+            saved.user_state.chains.get(BaseChain::FilterInput).policy,
+            NfChainPolicy::Accept
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use egui_node_graph::{InputId, NodeId, OutputId};
+
+use crate::nf_graph::NFDirection;
+use crate::NFGraph;
+
+pub fn receiving_node(graph: &NFGraph, output_id: OutputId) -> Option<NodeId> {
+    let connected_input = graph.connections.get(output_id)?;
+    let connected_input_node = graph.inputs.get(*connected_input)?;
+    Some(connected_input_node.node)
+}
+
+pub fn sending_nodes(graph: &NFGraph, input_id: InputId) -> Vec<NodeId> {
+    graph
+        .connections
+        .iter()
+        .filter(|(_, &iid)| iid == input_id)
+        .map(|(oid, _)| oid)
+        .filter_map(|oid| Some(graph.outputs.get(oid)?.node))
+        .collect()
+}
+
+pub fn connected_receiver_nodes(graph: &NFGraph, node_id: NodeId) -> Vec<NodeId> {
+    let Some(node) = graph.nodes.get(node_id) else {
+        return vec![];
+    };
+    node.outputs
+        .iter()
+        .filter_map(|(_, output_id)| receiving_node(graph, *output_id))
+        .collect()
+}
+
+pub fn connected_sender_nodes(graph: &NFGraph, node_id: NodeId) -> Vec<NodeId> {
+    let Some(node) = graph.nodes.get(node_id) else {
+        return vec![];
+    };
+    node.inputs
+        .iter()
+        .flat_map(|(_, input_id)| sending_nodes(graph, *input_id))
+        .collect()
+}
+
+/// Whether the connection of `output_id` leads back to its own node.
+pub fn closes_loop(graph: &NFGraph, output_id: OutputId) -> bool {
+    let Some(output) = graph.outputs.get(output_id) else {
+        return false;
+    };
+    let root_node_id = output.node;
+    let mut stack = vec![(0usize, connected_receiver_nodes(graph, root_node_id))];
+
+    while let Some((subtree_index, subtree_nodes)) = stack.last_mut() {
+        match subtree_nodes.get(*subtree_index).copied() {
+            Some(node_id) if node_id == root_node_id => return true,
+            Some(node_id) => {
+                *subtree_index += 1;
+                stack.push((0, connected_receiver_nodes(graph, node_id)));
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+    false
+}
+
+/// Passes the direction of packets leaving `output_id` on to the node
+/// receiving them and to everything downstream of it.
+pub fn propagate_data_types(
+    graph: &mut NFGraph,
+    input_id: InputId,
+    output_id: OutputId,
+) -> Option<()> {
+    let input = graph.inputs.get(input_id)?;
+    let output = graph.outputs.get(output_id)?;
+    if output.typ.direction == NFDirection::Either {
+        return Some(());
+    }
+    if input.typ.direction != NFDirection::Either {
+        return Some(());
+    }
+    propagate_to_node(graph, input_id, output.typ.direction)
+}
+
+fn propagate_to_node(graph: &mut NFGraph, input_id: InputId, direction: NFDirection) -> Option<()> {
+    graph.inputs.get_mut(input_id)?.typ.direction = direction;
+    let input = graph.inputs.get(input_id)?;
+    let node = graph.nodes.get(input.node)?;
+    for (_, output_id) in node.outputs.clone() {
+        graph.outputs.get_mut(output_id)?.typ.direction = direction;
+        if let Some(connected_input_id) = graph.connections.get(output_id) {
+            propagate_to_node(graph, *connected_input_id, direction);
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_node_graph::NodeTemplateTrait;
+
+    use super::*;
+    use crate::nf_graph::{NFGraphState, NFNodeData};
+
+    fn add_node(graph: &mut NFGraph, state: &mut NFGraphState, node: NFNodeData) -> NodeId {
+        graph.add_node(
+            node.node_graph_label(state),
+            node.user_data(state),
+            |graph, id| node.build_node(graph, state, id),
+        )
+    }
+
+    #[test]
+    fn test_loops_and_directions() {
+        let mut graph = NFGraph::new();
+        let mut state = NFGraphState::default();
+        let source = add_node(&mut graph, &mut state, NFNodeData::Source);
+        let first = add_node(
+            &mut graph,
+            &mut state,
+            NFNodeData::ProtocolFilter("tcp".into()),
+        );
+        let second = add_node(
+            &mut graph,
+            &mut state,
+            NFNodeData::InterfaceFilter("eth0".into()),
+        );
+        let input = |graph: &NFGraph, node: NodeId| graph[node].input_ids().next().unwrap();
+        let output = |graph: &NFGraph, node: NodeId, name| graph[node].get_output(name).unwrap();
+
+        let (incoming, first_input) = (output(&graph, source, "incoming"), input(&graph, first));
+        graph.add_connection(incoming, first_input);
+        propagate_data_types(&mut graph, first_input, incoming);
+        let (first_match, second_input) = (output(&graph, first, "match"), input(&graph, second));
+        graph.add_connection(first_match, second_input);
+        propagate_data_types(&mut graph, second_input, first_match);
+        let second_match = output(&graph, second, "match");
+        assert_eq!(graph[second_match].typ.direction, NFDirection::Incoming);
+        assert!(!closes_loop(&graph, second_match));
+
+        graph.add_connection(second_match, first_input);
+        assert!(closes_loop(&graph, second_match));
+        assert_eq!(connected_sender_nodes(&graph, first), [source, second]);
+    }
+}
@@ -0,0 +1,156 @@
+//! Compiles every graph in `tests/fixtures` and compares the result with
+//! the `expected.json` next to it, byte for byte. Run with
+//! `NETGRAPH_BLESS=1` to rewrite the expected files after an intended change
+//! of the compiler output, and with `--include-ignored` where `nft` can
+//! check them.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use netgraph_core::{bundle, Evaluator, SavedGraph};
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut fixtures: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("graph.json").exists())
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+/// Compiles a fixture into the contents an exported `nft.json` would have.
+fn compile(fixture: &Path) -> Result<String, String> {
+    let saved = SavedGraph::load(fixture).map_err(|e| e.to_string())?;
+    let evaluator = Evaluator::new(
+        &saved.editor_state.graph,
+        &saved.user_state.chains,
+        Some(fixture),
+    );
+    let invalid_nodes = evaluator.invalid_nodes();
+    if !invalid_nodes.is_empty() {
+        return Err(format!("invalid nodes: {invalid_nodes:?}"));
+    }
+    let (nft, _, diagnostics) = evaluator.compile().map_err(|e| e.to_string())?;
+    if !diagnostics.is_empty() {
+        let diagnostics: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+        return Err(diagnostics.join("\n"));
+    }
+    serde_json::to_string_pretty(&nft).map_err(|e| e.to_string())
+}
+
+/// Has `nft` check the file.
+fn nft_check(file: &Path) -> Result<(), String> {
+    let output = Command::new("nft")
+        .arg("-c")
+        .arg("-j")
+        .arg("-f")
+        .arg(file)
+        .output()
+        .map_err(|err| format!("cannot run nft: {err}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("nft rejects {}: {stderr}", file.display()))
+    }
+}
+
+#[test]
+fn test_golden_files() {
+    let bless = std::env::var_os("NETGRAPH_BLESS").is_some();
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures found");
+
+    let mut failures = vec![];
+    for fixture in &fixtures {
+        let name = fixture.file_name().unwrap().to_string_lossy();
+        let expected_path = fixture.join("expected.json");
+        let actual = match compile(fixture) {
+            Ok(actual) => actual,
+            Err(err) => {
+                failures.push(format!("{name}: {err}"));
+                continue;
+            }
+        };
+        if bless {
+            std::fs::write(&expected_path, &actual).unwrap();
+        }
+        match std::fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(_) => failures.push(format!(
+                "{name}: output differs from expected.json, run with NETGRAPH_BLESS=1 \
+                 and review the diff if the change is intended"
+            )),
+            Err(err) => failures.push(format!("{name}: cannot read expected.json: {err}")),
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs `nft` and the permission to check rulesets"]
+fn test_golden_files_load() {
+    let failures: Vec<_> = fixtures()
+        .iter()
+        .filter_map(|fixture| {
+            let name = fixture.file_name().unwrap().to_string_lossy().to_string();
+            let err = nft_check(&fixture.join("expected.json")).err()?;
+            Some(format!("{name}: {err}"))
+        })
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// Writes the bundle of the graph saved in `graph_dir` into `out`.
+fn export(graph_dir: &Path, out: &Path) {
+    let saved = SavedGraph::load(graph_dir).unwrap();
+    let evaluator = Evaluator::new(
+        &saved.editor_state.graph,
+        &saved.user_state.chains,
+        Some(graph_dir),
+    );
+    let (nft, routes, diagnostics) = evaluator.compile().unwrap();
+    assert!(diagnostics.is_empty());
+    let nft = serde_json::to_string_pretty(&nft).unwrap();
+    std::fs::create_dir_all(out).unwrap();
+    bundle::write_bundle(out, &nft, &routes).unwrap();
+}
+
+#[test]
+fn test_reexport_is_identical() {
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/router_nat");
+    let dir = std::env::temp_dir().join(format!("netgraph-reexport-{}", std::process::id()));
+    export(&fixture, &dir.join("first"));
+
+    // saving the graph again and moving its nodes leaves the chain names,
+    // which are derived from the nodes, as they were
+    let mut saved = SavedGraph::load(&fixture).unwrap();
+    for position in saved.editor_state.node_positions.values_mut() {
+        position.x += 40.0;
+        position.y += 25.0;
+    }
+    std::fs::create_dir_all(dir.join("graph")).unwrap();
+    saved.save(&dir.join("graph")).unwrap();
+    export(&dir.join("graph"), &dir.join("second"));
+
+    let files = |bundle: &str| {
+        let mut files: Vec<_> = std::fs::read_dir(dir.join(bundle))
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                (
+                    path.file_name().unwrap().to_owned(),
+                    std::fs::read(&path).unwrap(),
+                )
+            })
+            .collect();
+        files.sort();
+        files
+    };
+    let (first, second) = (files("first"), files("second"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(!first.is_empty());
+    assert!(first == second, "re-exported bundle differs");
+}